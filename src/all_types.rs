@@ -2,8 +2,18 @@ use rand::prelude::IteratorRandom;
 use std::ops::{Index, IndexMut};
 
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[repr(transparent)]
 pub struct Lit(u32);
 impl Lit {
+    #[inline]
+    pub fn new(var: Var, neg: bool) -> Lit {
+        Lit((var.0 << 1) + neg as u32)
+    }
+    /// The integer representing the literal
+    #[inline]
+    pub fn code(self) -> u32 {
+        self.0
+    }
     #[inline]
     pub fn get_var(self) -> Var {
         Var(self.0 >> 1)
@@ -16,6 +26,11 @@ impl Lit {
     pub fn is_neg(&self) -> bool {
         self.0 & 1 != 0
     }
+    /// The value of the literal under the values of the vars
+    #[inline]
+    pub fn value(self, values: &[BoolValue]) -> BoolValue {
+        BoolValue::from(values[self.get_var().0 as usize] as i8 ^ self.is_neg() as i8)
+    }
 }
 impl From<i32> for Lit {
     #[inline]
@@ -29,6 +44,18 @@ impl From<i32> for Lit {
         }
     }
 }
+impl From<Lit> for i32 {
+    /// The dimacs representation of the literal
+    #[inline]
+    fn from(lit: Lit) -> Self {
+        let x = (lit.0 >> 1) as i32 + 1;
+        if lit.is_neg() {
+            -x
+        } else {
+            x
+        }
+    }
+}
 impl std::ops::Not for Lit {
     type Output = Self;
     #[inline]
@@ -74,6 +101,18 @@ impl<T> IndexMut<Var> for Vec<T> {
 
 pub type Clause = Vec<Lit>;
 
+/// Sort the clause and remove its duplicate literals, None if it is a tautology
+pub fn normalize(mut clause: Clause) -> Option<Clause> {
+    clause.sort();
+    clause.dedup();
+    // x and ¬x are next to each other once sorted
+    if clause.windows(2).any(|pair| pair[0] == !pair[1]) {
+        None
+    } else {
+        Some(clause)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CClause<'a> {
     clause: &'a Vec<Lit>,
@@ -100,16 +139,16 @@ impl<'a> CAllClauses<'a> {
     }
 }
 
-#[derive(Debug, Default, Clone)]
-
-pub struct AllClauses {
-    pub clauses: Vec<Clause>,
-}
+/// The position of a clause in the clause arena of the CDCL solver
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ClauseRef(pub u32);
 
-impl AllClauses {
-    pub fn push(&mut self, clause: Clause) {
-        self.clauses.push(clause);
-    }
+/// A clause watching a literal, along with a blocker literal of the same clause.
+/// If the blocker is true the clause is satisfied and does not need to be visited.
+#[derive(Debug, Clone, Copy)]
+pub struct Watcher {
+    pub clause: ClauseRef,
+    pub blocker: Lit,
 }
 
 #[derive(Debug, Clone)]
@@ -149,18 +188,22 @@ impl std::ops::Not for BoolValue {
     }
 }
 
-#[derive(Debug, Default)]
-/// If p in impl_graph[q] then p goes to q in the implication graph
-pub struct ImplGraph(Vec<Vec<Lit>>);
-
 #[derive(Debug)]
 pub struct WorkingModel {
     // The working assignment of the model
     assigns: Vec<BoolValue>,
     // The decision level of each var
     decision_level: Vec<usize>,
-    // The implication graph
-    impl_graph: ImplGraph,
+    // The clause that implied the assignment of each var, None for decisions and units
+    reason: Vec<Option<ClauseRef>>,
+    // The assigned literals in chronological order
+    trail: Vec<Lit>,
+    // The position in the trail where each decision level starts
+    trail_lim: Vec<usize>,
+    // The position in the trail of the next literal to propagate
+    qhead: usize,
+    // The last value of each var before it was unassigned
+    saved_phase: Vec<BoolValue>,
 }
 
 impl WorkingModel {
@@ -168,12 +211,44 @@ impl WorkingModel {
         WorkingModel {
             assigns: vec![BoolValue::Undefined; n],
             decision_level: vec![0; n],
-            impl_graph: ImplGraph(vec![Vec::new(); n]),
+            reason: vec![None; n],
+            trail: Vec::with_capacity(n),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            saved_phase: vec![BoolValue::False; n],
         }
     }
-    pub fn assign(&mut self, var: Var, value: BoolValue, level: usize) {
-        self.assigns[var] = value;
-        self.decision_level[var] = level;
+    /// Add an unassigned var
+    pub fn new_var(&mut self) {
+        self.assigns.push(BoolValue::Undefined);
+        self.decision_level.push(0);
+        self.reason.push(None);
+        self.saved_phase.push(BoolValue::False);
+    }
+    /// Set the literal to true at the current decision level,
+    /// the reason is the clause that became unit if any
+    pub fn assign(&mut self, lit: Lit, reason: Option<ClauseRef>) {
+        let var = lit.get_var();
+        self.assigns[var] = BoolValue::from(lit.is_neg() as i8);
+        self.decision_level[var] = self.trail_lim.len();
+        self.reason[var] = reason;
+        self.trail.push(lit);
+    }
+    /// Open a new decision level
+    pub fn new_decision_level(&mut self) {
+        self.trail_lim.push(self.trail.len());
+    }
+    #[inline]
+    pub fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+    /// The next assigned literal whose consequences are not propagated yet
+    pub fn next_to_propagate(&mut self) -> Option<Lit> {
+        let lit = self.trail.get(self.qhead).copied();
+        if lit.is_some() {
+            self.qhead += 1;
+        }
+        lit
     }
     #[inline]
     pub fn level(&self, v: Var) -> usize {
@@ -181,111 +256,32 @@ impl WorkingModel {
     }
     #[inline]
     pub fn eval(&self, lit: Lit) -> BoolValue {
-        BoolValue::from(self.assigns[lit.get_var()] as i8 ^ lit.is_neg() as i8)
-    }
-
-    /// adds implication in implication graph
-    /// by taking unit clause and unnassigned variable that is to be set true in argument
-    pub fn add_implications(&mut self, var: Var, clause: &Clause) {
-        for lit in clause.iter() {
-            if lit.get_var() != var {
-                self.impl_graph.0[var].push(!*lit)
-            }
-        }
+        lit.value(&self.assigns)
     }
-    pub fn find_conflict(&self, conflict: &Clause) -> Clause {
-        // backtracking the implication graph to find the sources of the conflict
-        // creates the conflict clause
-        let mut stack = Vec::new();
 
-        let mut conflict_clause = Vec::new();
-        for lit in conflict {
-            stack.push(!*lit)
-        }
-        while let Some(lit) = stack.pop() {
-            if self.impl_graph.0[lit.get_var()].is_empty() && !conflict_clause.contains(&!lit) {
-                conflict_clause.push(!lit)
-            } else {
-                for lit_dep in &self.impl_graph.0[lit.get_var()] {
-                    stack.push(*lit_dep)
-                }
-            }
-        }
-        conflict_clause
-    }
-
-    /// evaluate the state of each clause
-    pub fn state_clause(&self, clause: &Clause) -> BoolValue {
-        let mut state_clause = BoolValue::False;
-        for lit in clause {
-            match self.eval(*lit) {
-                BoolValue::True => {
-                    return BoolValue::True;
-                }
-                BoolValue::Undefined => {
-                    state_clause = BoolValue::Undefined;
-                }
-                _ => {}
-            }
-        }
-        state_clause
+    #[inline]
+    pub fn reason(&self, v: Var) -> Option<ClauseRef> {
+        self.reason[v]
     }
-
-    /// evaluate the state of the formula
-    pub fn state_formula(&self, formula: &AllClauses) -> BoolValue {
-        let mut is_undefined = false;
-        for clause in &formula.clauses {
-            match self.state_clause(clause) {
-                BoolValue::False => return BoolValue::False,
-                BoolValue::Undefined => is_undefined = true,
-                _ => {}
-            }
-        }
-        if is_undefined {
-            BoolValue::Undefined
-        } else {
-            BoolValue::True
+    /// Update the reasons of the assigned vars once the clauses are moved
+    pub fn relocate_reasons(&mut self, mut relocate: impl FnMut(ClauseRef) -> ClauseRef) {
+        for lit in self.trail.iter() {
+            let var = lit.get_var();
+            self.reason[var] = self.reason[var].map(&mut relocate);
         }
     }
-
-    /// find conflict when state of the formula is false
-    pub fn conflicting(&self, formula: &AllClauses) -> Option<Clause> {
-        for clause in &formula.clauses {
-            if self.state_clause(clause) == BoolValue::False {
-                return Some(clause.to_vec());
-            }
-        }
-        None
+    #[inline]
+    pub fn trail(&self) -> &[Lit] {
+        &self.trail
     }
-
-    /// Checks whether a clause is a unit clause
-    /// ie all its literals are false except one which is undefined
-    pub fn is_unit_clause(&self, clause: &Clause) -> Option<Lit> {
-        let mut undefined_lit = None;
-        for lit in clause {
-            match self.eval(*lit) {
-                BoolValue::True => return None,
-                BoolValue::Undefined => {
-                    if undefined_lit.is_some() {
-                        return None;
-                    } else {
-                        undefined_lit = Some(*lit)
-                    }
-                }
-                _ => {}
-            }
+    /// The literals assigned above the given level
+    pub fn trail_above(&self, level: usize) -> &[Lit] {
+        match self.trail_lim.get(level) {
+            Some(&start) => &self.trail[start..],
+            None => &[],
         }
-        undefined_lit
     }
 
-    pub fn next_unassigned(&self) -> Var {
-        for i in 0..self.assigns.len() {
-            if self.assigns[i] == BoolValue::Undefined {
-                return Var::from_id(i);
-            }
-        }
-        panic!("no variable ?")
-    }
     #[allow(dead_code)]
     pub fn random_unassigned(&self) -> Var {
         Var::from_id(
@@ -295,18 +291,31 @@ impl WorkingModel {
                 .unwrap(),
         )
     }
+    #[inline]
+    pub fn saved_phase(&self, v: Var) -> BoolValue {
+        self.saved_phase[v]
+    }
+    pub fn set_saved_phase(&mut self, v: Var, value: BoolValue) {
+        self.saved_phase[v] = value;
+    }
     pub fn get_assigned(&self) -> &Vec<BoolValue> {
         &self.assigns
     }
-    //implements backtracking : modifies the working model
+    /// Implements backtracking: undo the assignments made above the given level, most recent first
     pub fn backtracking(&mut self, level: usize) {
-        for ind in 0..self.assigns.len() {
-            if self.decision_level[ind] > level {
-                self.decision_level[ind] = 0;
-                self.assigns[ind] = BoolValue::Undefined;
-                self.impl_graph.0[ind] = Vec::new();
-            }
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for &lit in self.trail[start..].iter().rev() {
+            let var = lit.get_var();
+            self.decision_level[var] = 0;
+            self.saved_phase[var] = self.assigns[var];
+            self.assigns[var] = BoolValue::Undefined;
+            self.reason[var] = None;
         }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.qhead = start;
     }
 }
-
@@ -0,0 +1,288 @@
+use crate::all_types::*;
+use crate::config::Branching;
+
+/// Every this many conflicts, Chaff's VSIDS decays all the activities.
+const VSIDS_DECAY_INTERVAL: u64 = 256;
+/// Activities are rescaled when one of them grows above this limit to avoid overflows.
+const ACTIVITY_LIMIT: f64 = 1e100;
+
+/// Activity based order, the unassigned variable with the highest activity is picked first.
+/// The variables are kept in a binary max heap on their activity.
+#[derive(Debug)]
+pub struct Vsids {
+    activity: Vec<f64>,
+    heap: Vec<Var>,
+    // The position of each var in the heap, if it is in it
+    position: Vec<Option<usize>>,
+    increment: f64,
+    decay: f64,
+    // Whether the increment grows (EVSIDS) or the activities are periodically decayed (VSIDS)
+    exponential: bool,
+    conflicts: u64,
+}
+
+impl Vsids {
+    fn new(n: usize, decay: f64, exponential: bool) -> Self {
+        Vsids {
+            activity: vec![0.; n],
+            heap: (0..n).map(Var::from_id).collect(),
+            position: (0..n).map(Some).collect(),
+            increment: 1.,
+            decay,
+            exponential,
+            conflicts: 0,
+        }
+    }
+
+    fn new_var(&mut self, var: Var) {
+        self.activity.push(0.);
+        self.position.push(None);
+        self.insert(var);
+    }
+
+    fn bump(&mut self, var: Var) {
+        self.activity[var] += self.increment;
+        if self.activity[var] > ACTIVITY_LIMIT {
+            self.activity
+                .iter_mut()
+                .for_each(|act| *act /= ACTIVITY_LIMIT);
+            self.increment /= ACTIVITY_LIMIT;
+        }
+        if let Some(pos) = self.position[var] {
+            self.sift_up(pos);
+        }
+    }
+
+    fn decay(&mut self) {
+        self.conflicts += 1;
+        if self.exponential {
+            self.increment /= self.decay;
+        } else if self.conflicts.is_multiple_of(VSIDS_DECAY_INTERVAL) {
+            // Multiplying every activity keeps the heap ordered
+            let decay = self.decay;
+            self.activity.iter_mut().for_each(|act| *act *= decay);
+        }
+    }
+
+    fn insert(&mut self, var: Var) {
+        if self.position[var].is_none() {
+            self.position[var] = Some(self.heap.len());
+            self.heap.push(var);
+            self.sift_up(self.heap.len() - 1);
+        }
+    }
+
+    fn pop(&mut self) -> Option<Var> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.position[top] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.position[last] = Some(0);
+            self.sift_down(0);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        let var = self.heap[pos];
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if self.activity[self.heap[parent]] >= self.activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[parent];
+            self.position[self.heap[pos]] = Some(pos);
+            pos = parent;
+        }
+        self.heap[pos] = var;
+        self.position[var] = Some(pos);
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        let var = self.heap[pos];
+        loop {
+            let left = 2 * pos + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && self.activity[self.heap[right]] > self.activity[self.heap[left]]
+            {
+                right
+            } else {
+                left
+            };
+            if self.activity[self.heap[child]] <= self.activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[child];
+            self.position[self.heap[pos]] = Some(pos);
+            pos = child;
+        }
+        self.heap[pos] = var;
+        self.position[var] = Some(pos);
+    }
+}
+
+/// Variable move to front order.
+/// The variables are kept in a doubly linked queue sorted by the time of their last bump,
+/// the most recently bumped unassigned variable is picked first.
+#[derive(Debug)]
+pub struct Vmtf {
+    prev: Vec<Option<Var>>,
+    next: Vec<Option<Var>>,
+    // The time of the last bump of each var
+    stamp: Vec<u64>,
+    first: Option<Var>,
+    last: Option<Var>,
+    // Every var bumped more recently than this one is assigned
+    search: Option<Var>,
+    time: u64,
+}
+
+impl Vmtf {
+    fn new(n: usize) -> Self {
+        Vmtf {
+            prev: (0..n).map(|i| i.checked_sub(1).map(Var::from_id)).collect(),
+            next: (0..n)
+                .map(|i| Some(Var::from_id(i + 1)).filter(|_| i + 1 < n))
+                .collect(),
+            stamp: (0..n as u64).collect(),
+            first: Some(Var::from_id(0)).filter(|_| n > 0),
+            last: n.checked_sub(1).map(Var::from_id),
+            search: n.checked_sub(1).map(Var::from_id),
+            time: n as u64,
+        }
+    }
+
+    /// Append the variable at the end of the queue
+    fn new_var(&mut self, var: Var) {
+        self.prev.push(self.last);
+        self.next.push(None);
+        match self.last {
+            Some(l) => self.next[l] = Some(var),
+            None => self.first = Some(var),
+        }
+        self.last = Some(var);
+        self.time += 1;
+        self.stamp.push(self.time);
+        self.unassigned(var);
+    }
+
+    /// Move the variable at the end of the queue
+    fn move_to_front(&mut self, var: Var) {
+        if self.last == Some(var) {
+            self.time += 1;
+            self.stamp[var] = self.time;
+            return;
+        }
+        // Unlink
+        let (prev, next) = (self.prev[var], self.next[var]);
+        match prev {
+            Some(p) => self.next[p] = next,
+            None => self.first = next,
+        }
+        if let Some(n) = next {
+            self.prev[n] = prev;
+        }
+        if self.search == Some(var) {
+            self.search = prev.or(next);
+        }
+        // Append
+        self.prev[var] = self.last;
+        self.next[var] = None;
+        if let Some(l) = self.last {
+            self.next[l] = Some(var);
+        }
+        self.last = Some(var);
+        self.time += 1;
+        self.stamp[var] = self.time;
+    }
+
+    fn unassigned(&mut self, var: Var) {
+        if self.search.is_none_or(|s| self.stamp[var] > self.stamp[s]) {
+            self.search = Some(var);
+        }
+    }
+
+    fn next(&mut self, model: &WorkingModel) -> Option<Var> {
+        let mut current = self.search;
+        while let Some(var) = current {
+            if model.eval(Lit::new(var, false)) == BoolValue::Undefined {
+                break;
+            }
+            current = self.prev[var];
+        }
+        self.search = current.or(self.first);
+        current
+    }
+}
+
+/// The order in which the decision variables are picked.
+#[derive(Debug)]
+pub enum VarOrder {
+    Vsids(Vsids),
+    Vmtf(Vmtf),
+}
+
+impl VarOrder {
+    pub fn new(n: usize, branching: Branching, decay: f64) -> Self {
+        match branching {
+            Branching::Vsids => VarOrder::Vsids(Vsids::new(n, decay, false)),
+            Branching::Evsids => VarOrder::Vsids(Vsids::new(n, decay, true)),
+            Branching::Vmtf => VarOrder::Vmtf(Vmtf::new(n)),
+        }
+    }
+
+    /// Add a variable, which has the lowest priority for VSIDS and the highest for VMTF
+    pub fn new_var(&mut self, var: Var) {
+        match self {
+            VarOrder::Vsids(vsids) => vsids.new_var(var),
+            VarOrder::Vmtf(vmtf) => vmtf.new_var(var),
+        }
+    }
+
+    /// Bump the variables met during a conflict analysis
+    pub fn bump(&mut self, vars: &mut [Var]) {
+        match self {
+            VarOrder::Vsids(vsids) => vars.iter().for_each(|&var| vsids.bump(var)),
+            VarOrder::Vmtf(vmtf) => {
+                // Keep the relative order of the bumped variables
+                vars.sort_unstable_by_key(|&var| vmtf.stamp[var]);
+                vars.iter().for_each(|&var| vmtf.move_to_front(var));
+            }
+        }
+    }
+
+    /// Called once per conflict, after bumping
+    pub fn decay(&mut self) {
+        if let VarOrder::Vsids(vsids) = self {
+            vsids.decay()
+        }
+    }
+
+    /// Called when the variable is unassigned by backtracking so it can be picked again
+    pub fn unassigned(&mut self, var: Var) {
+        match self {
+            VarOrder::Vsids(vsids) => vsids.insert(var),
+            VarOrder::Vmtf(vmtf) => vmtf.unassigned(var),
+        }
+    }
+
+    /// The next decision variable, None if every variable is assigned
+    pub fn next(&mut self, model: &WorkingModel) -> Option<Var> {
+        match self {
+            VarOrder::Vsids(vsids) => {
+                while let Some(var) = vsids.pop() {
+                    if model.eval(Lit::new(var, false)) == BoolValue::Undefined {
+                        return Some(var);
+                    }
+                }
+                None
+            }
+            VarOrder::Vmtf(vmtf) => vmtf.next(model),
+        }
+    }
+}
@@ -1,156 +1,1117 @@
+use crate::branching::VarOrder;
+use crate::clause_arena::ClauseArena;
+use crate::config::{CdclConfig, Minimization};
+use crate::phases::Phases;
+use crate::proof::Proof;
+use crate::restart::Restarts;
+use crate::solver::{Budget, Limits, SolveResult, Terminator};
+use crate::subsumption::{Simplification, Subsumer};
 use crate::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashSet;
+
+/// Learnt clauses with at most this LBD are never deleted
+const GLUE_LBD: u32 = 2;
+/// Clause activities are rescaled when one of them grows above this limit to avoid overflows.
+const CLAUSE_ACTIVITY_LIMIT: f32 = 1e20;
+/// Binary implications are used to minimize the learnt clauses with at most this LBD
+const BINARY_MINIMIZATION_LBD: u32 = 6;
+/// The clause arena is compacted when this fraction of it is wasted by deleted clauses
+const GARBAGE_FRACTION: f64 = 0.2;
+/// The number of literals a vivification may propagate before it stops
+const VIVIFY_EFFORT: usize = 100_000;
+
+/// The work done by the solver on its clauses besides the search
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    /// The learnt clauses deleted by the reductions
+    pub reduced: u64,
+    /// The compactions of the clause arena
+    pub collections: u64,
+    /// The learnt clauses removed because another clause subsumes them
+    pub subsumed: u64,
+    /// The learnt clauses strengthened by self-subsuming resolution
+    pub strengthened: u64,
+    /// The clauses shortened by vivification
+    pub vivified: u64,
+}
 
 /// A CDCL solver.
 /// Clause Driven Conflict Learning is an algorithm that solves SAT in an amortized exponential time.
 /// The amortized part allows us to be "efficient" on real input, that is to say that we postpone the exponential growth enough to make it usable.
+/// The solver is incremental: clauses and vars can be added between calls to `solve_with_assumptions`,
+/// and what was learnt is kept.
 #[derive(Debug)]
 pub struct CdclSolver {
-    // The clauses (initial and added ones)
-    clauses: AllClauses,
-    // Assignments of vars and decision levels of the assignments
-    // and the implication graph
+    // The clauses (initial and learnt ones)
+    clauses: ClauseArena,
+    // The references of the initial and learnt clauses of size at least 2
+    originals: Vec<ClauseRef>,
+    learnts: Vec<ClauseRef>,
+    // For each literal, the clauses in which it is watched.
+    // The two first literals of each clause (of size at least 2) are the watched ones.
+    watches: Vec<Vec<Watcher>>,
+    // Assignments of vars and decision levels of the assignments,
+    // the trail of assigned literals and their reasons
     working_model: WorkingModel,
-    // Wether it is sat or not
+    // Vars met during conflict analysis
+    seen: Vec<bool>,
+    // The vars to bump at the end of the conflict analysis
+    to_bump: Vec<Var>,
+    // The literals whose var has to be unmarked at the end of the conflict analysis
+    to_clear: Vec<Lit>,
+    // The order in which decision vars are picked
+    order: VarOrder,
+    // The value given to decision vars
+    phases: Phases,
+    restarts: Restarts,
+    conflicts: u64,
+    // The number of conflicts before the next reduction of the learnt clauses
+    next_reduce: u64,
+    reduce_interval: u64,
+    // The number of conflicts before the next subsumption of the learnt clauses
+    next_subsume: u64,
+    // The clauses with a lower id were already simplified by subsumption
+    subsumed_id: u64,
+    // The number of conflicts before the next vivification
+    next_vivify: u64,
+    // The amount by which clause activities are bumped
+    clause_increment: f32,
+    // For each decision level, the last time it was counted in a literal block distance
+    level_stamp: Vec<u64>,
+    lbd_time: u64,
+    config: CdclConfig,
+    rng: StdRng,
+    // The literals assumed true during the current call, the i-th one is decided at level i + 1
+    assumptions: Vec<Lit>,
+    // The assumptions responsible for the unsatisfiability of the last call
+    failed: Vec<Lit>,
+    // Where the learnt and deleted clauses are written, if a proof is asked
+    proof: Option<Proof>,
+    // The id of the next clause
+    next_id: u64,
+    // For each var assigned at level 0, the id of the unit clause proving it, if a proof is asked
+    unit_id: Vec<u64>,
+    // The binary clauses implying the literals removed by binary minimization, for LRAT
+    binary_reasons: Vec<(Var, ClauseRef)>,
+    // The LRAT hints of the last learnt clause
+    hints: Vec<u64>,
+    // The resources used by the current call and its limits
+    budget: Budget,
+    stats: Stats,
+    // The model found by the last call, if it was satisfiable
+    model: Vec<BoolValue>,
+    // Some(false) once the formula is unsatisfiable whatever the assumptions,
+    // Some(true) if the last call found a model
     pub status: Option<bool>,
-    level: usize,
 }
 
 impl<'a> solver::Solver<'a> for CdclSolver {
     fn new<'b: 'a>(clauses: &Cnf) -> Self {
+        CdclSolver::with_config(clauses, CdclConfig::default())
+    }
+
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    fn solve(&mut self) -> SolveResult {
+        self.solve_with_assumptions(&[])
+    }
+
+    fn assigns(&mut self) -> &Vec<BoolValue> {
+        &self.model
+    }
+}
+
+impl CdclSolver {
+    pub fn with_config(clauses: &Cnf, config: CdclConfig) -> Self {
+        CdclSolver::with_proof(clauses, config, None)
+    }
+
+    /// Create a solver writing the proof of its calls, if any
+    pub fn with_proof(clauses: &Cnf, config: CdclConfig, proof: Option<Proof>) -> Self {
         let n = clauses.var_num;
         let mut solver = CdclSolver {
-            clauses: AllClauses { clauses: vec![] },
+            clauses: ClauseArena::default(),
+            originals: vec![],
+            learnts: vec![],
+            watches: vec![Vec::new(); 2 * n],
             working_model: WorkingModel::new(n),
+            seen: vec![false; n],
+            to_bump: vec![],
+            to_clear: vec![],
+            order: VarOrder::new(n, config.branching, config.var_decay),
+            phases: Phases::new(n, config.polarity),
+            restarts: Restarts::new(config.restart),
+            conflicts: 0,
+            next_reduce: config.reduce_interval,
+            reduce_interval: config.reduce_interval,
+            next_subsume: config.subsume_interval,
+            subsumed_id: 0,
+            next_vivify: config.vivify_interval,
+            clause_increment: 1.,
+            level_stamp: vec![0; n + 1],
+            lbd_time: 0,
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            assumptions: vec![],
+            failed: vec![],
+            proof,
+            next_id: clauses.clauses.len() as u64 + 1,
+            unit_id: vec![0; n],
+            binary_reasons: vec![],
+            hints: vec![],
+            budget: Budget::default(),
+            stats: Stats::default(),
+            model: vec![BoolValue::Undefined; n],
             status: None,
-            level: 0,
         };
-        clauses.clauses.iter().for_each(|clause| {
-            if clause.is_empty() {
-                solver.status = Some(false);
-            } else {
-                solver.add_clause(clause.to_vec());
-            }
-        });
+        // The original clauses are numbered from 1 in order
+        for (id, clause) in (1..).zip(clauses.clauses.iter()) {
+            solver.add_clause_with_id(clause.to_vec(), id, None);
+        }
         solver
     }
 
-    fn solve(&mut self) -> bool {
-        if let Some(status) = self.status {
-            return status;
+    pub fn num_vars(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Add a var to the solver and return it
+    pub fn new_var(&mut self) -> Var {
+        let var = Var::from_id(self.num_vars());
+        self.watches.push(vec![]);
+        self.watches.push(vec![]);
+        self.working_model.new_var();
+        self.seen.push(false);
+        self.order.new_var(var);
+        self.phases.new_var();
+        self.level_stamp.push(0);
+        self.model.push(BoolValue::Undefined);
+        self.unit_id.push(0);
+        var
+    }
+
+    fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Add to the proof the clause obtained by removing from the clause with the given id some literals
+    /// false at level 0. Its hints are the units falsifying them, then the clause. Returns its id.
+    fn add_strengthened(&mut self, clause: &[Lit], removed: &[Lit], id: u64) -> u64 {
+        let new_id = self.new_id();
+        if let Some(proof) = &mut self.proof {
+            let hints: Vec<u64> = removed
+                .iter()
+                .map(|lit| self.unit_id[lit.get_var()])
+                .chain([id])
+                .collect();
+            proof.add(new_id, clause, &hints);
         }
-        self.cdcl()
+        new_id
     }
 
-    fn assigns(&mut self) -> &Vec<BoolValue> {
-        self.working_model.get_assigned()
+    /// Create the vars of the literals that do not exist yet
+    fn add_vars_of(&mut self, lits: &[Lit]) {
+        if let Some(max) = lits.iter().map(|lit| lit.get_var().0 as usize).max() {
+            while self.num_vars() <= max {
+                self.new_var();
+            }
+        }
     }
-}
 
-impl CdclSolver {
+    /// Add a clause to the solver, the vars it contains are created if needed.
+    /// Returns false if the formula became trivially unsatisfiable.
+    #[cfg(test)]
     pub fn add_clause(&mut self, clause: Clause) -> bool {
-        if clause.len() == 1 {
-            let lit = clause[0];
-            if self.working_model.eval(lit) == BoolValue::False {
+        let id = self.new_id();
+        self.add_clause_with_id(clause, id, None)
+    }
+
+    /// Add the clause, which is learnt with the given LBD if there is one
+    fn add_clause_with_id(&mut self, clause: Clause, mut id: u64, lbd: Option<u32>) -> bool {
+        // The clause is simplified by the assignments of level 0 only
+        self.backtrack(0);
+        if self.status == Some(false) {
+            return false;
+        }
+        self.status = None;
+        self.add_vars_of(&clause);
+        let Some(clause) = normalize(clause) else {
+            return true;
+        };
+        if clause
+            .iter()
+            .any(|&lit| self.working_model.eval(lit) == BoolValue::True)
+        {
+            return true;
+        }
+        let (kept, removed): (Clause, Clause) = clause
+            .iter()
+            .partition(|&&lit| self.working_model.eval(lit) == BoolValue::Undefined);
+        if !removed.is_empty() {
+            id = self.add_strengthened(&kept, &removed, id);
+        }
+        match kept.len() {
+            0 => {
                 self.status = Some(false);
-                return false;
+                false
+            }
+            1 => {
+                self.unit_id[kept[0].get_var()] = id;
+                self.enqueue(kept[0], None);
+                true
             }
-            self.working_model.assign(
-                lit.get_var(),
-                BoolValue::from(lit.is_neg() as i8),
-                self.level,
-            )
+            _ => {
+                self.attach(&kept, lbd.is_some(), lbd.unwrap_or(0), id);
+                true
+            }
+        }
+    }
+
+    /// Store the clause and watch its two first literals
+    fn attach(&mut self, clause: &[Lit], learnt: bool, lbd: u32, id: u64) -> ClauseRef {
+        let cref = self.clauses.alloc(clause, learnt, lbd, id);
+        if learnt {
+            self.learnts.push(cref);
         } else {
-            self.clauses.push(clause);
+            self.originals.push(cref);
         }
-        true
+        self.watch(cref);
+        cref
     }
 
-    /// Implement the CDCL algorithm
-    fn cdcl(&mut self) -> bool {
-        self.propagate();
+    fn watch(&mut self, cref: ClauseRef) {
+        let (first, second) = (self.clauses[cref][0], self.clauses[cref][1]);
+        self.watches[first].push(Watcher {
+            clause: cref,
+            blocker: second,
+        });
+        self.watches[second].push(Watcher {
+            clause: cref,
+            blocker: first,
+        });
+    }
+
+    fn unwatch(&mut self, cref: ClauseRef) {
+        for lit in [self.clauses[cref][0], self.clauses[cref][1]] {
+            self.watches[lit].retain(|watcher| watcher.clause != cref);
+        }
+    }
+
+    /// Set the literal to true at the current level, its propagation is scheduled by the trail.
+    /// The reason is the clause that implied it, if any.
+    fn enqueue(&mut self, lit: Lit, reason: Option<ClauseRef>) {
+        self.working_model.assign(lit, reason);
+        if self.proof.is_some() && self.working_model.decision_level() == 0 {
+            if let Some(cref) = reason {
+                // The unit is proved once so that the proofs can refer to it
+                let removed = self.clauses[cref][1..].to_vec();
+                let id = self.clauses.id(cref);
+                self.unit_id[lit.get_var()] = self.add_strengthened(&[lit], &removed, id);
+            }
+        }
+    }
+
+    /// Solve the formula with the assumptions, literals that are true during this call only.
+    /// Returns Unsat if the formula is unsatisfiable under the assumptions, and Unknown if the limits
+    /// are reached first.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> SolveResult {
+        self.budget.reset();
+        self.backtrack(0);
+        self.add_vars_of(assumptions);
+        self.assumptions = assumptions.to_vec();
+        self.failed.clear();
+        // Each assumption opens its own decision level
+        self.level_stamp
+            .resize(self.num_vars() + assumptions.len() + 1, 0);
+        let result = if self.status == Some(false) {
+            SolveResult::Unsat
+        } else {
+            self.cdcl()
+        };
+        if result == SolveResult::Sat {
+            self.model.clone_from(self.working_model.get_assigned());
+        }
+        if let Some(proof) = &mut self.proof {
+            proof.flush();
+        }
+        result
+    }
 
+    /// Implement the CDCL algorithm
+    fn cdcl(&mut self) -> SolveResult {
         loop {
-            while self.working_model.state_formula(&self.clauses) == BoolValue::False {
-                if self.level == 0 {
+            if let Some(conflict) = self.propagate() {
+                if self.working_model.decision_level() == 0 {
+                    // The empty clause ends the proof of unsatisfiability
+                    let clause = self.clauses[conflict].to_vec();
+                    self.add_strengthened(&[], &clause, self.clauses.id(conflict));
                     self.status = Some(false);
-                    return false;
+                    return SolveResult::Unsat;
                 }
-                let (lvl, learnt) = self.analyze_conflict();
-                self.backtrack(lvl as usize);
-                if !self.add_clause(learnt) {
-                    return false;
+                self.conflicts += 1;
+                self.budget.conflicts += 1;
+                self.phases.on_conflict(&self.working_model);
+                let (lvl, learnt) = self.analyze_conflict(conflict);
+                let lbd = self.lbd(&learnt);
+                self.restarts.on_conflict(lbd);
+                self.backtrack(lvl);
+                self.learn(learnt, lbd);
+            } else if self.restarts.should_restart() {
+                // Learnt clauses, activities and saved phases are kept
+                self.backtrack(0);
+                self.restarts.restarted();
+                if self.config.vivify_interval > 0 && self.conflicts >= self.next_vivify {
+                    self.vivify();
+                    if self.status == Some(false) {
+                        return SolveResult::Unsat;
+                    }
+                }
+            } else if self.conflicts >= self.next_reduce {
+                self.reduce_db();
+            } else if self.config.subsume_interval > 0 && self.conflicts >= self.next_subsume {
+                self.backtrack(0);
+                self.subsume_learnts();
+                if self.status == Some(false) {
+                    return SolveResult::Unsat;
+                }
+            } else if self.phases.should_rephase(self.conflicts) {
+                self.backtrack(0);
+                self.phases
+                    .rephase(self.conflicts, &mut self.working_model, &mut self.rng);
+            } else if self.budget.exhausted() {
+                self.status = None;
+                return SolveResult::Unknown;
+            } else if self.working_model.decision_level() < self.assumptions.len() {
+                let lit = self.assumptions[self.working_model.decision_level()];
+                match self.working_model.eval(lit) {
+                    // Open an empty level so that the next assumption gets the next one
+                    BoolValue::True => self.working_model.new_decision_level(),
+                    BoolValue::False => {
+                        self.analyze_final(lit);
+                        return SolveResult::Unsat;
+                    }
+                    BoolValue::Undefined => {
+                        self.working_model.new_decision_level();
+                        self.enqueue(lit, None);
+                    }
+                }
+            } else if let Some(var) = self.pick_branching_var() {
+                self.working_model.new_decision_level();
+                self.budget.decisions += 1;
+                self.decide(var);
+            } else {
+                self.status = Some(true);
+                return SolveResult::Sat;
+            }
+        }
+    }
+
+    /// Count the time of the next call from this instant, its time limit then includes what was done
+    /// since then
+    pub fn start_at(&mut self, start: std::time::Instant) {
+        self.budget.start_at(start);
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The first error met while writing the proof, the proof is incomplete if there is one
+    pub fn proof_error(&self) -> Option<&std::io::Error> {
+        self.proof.as_ref().and_then(|proof| proof.error())
+    }
+
+    /// The subset of the assumptions of the last call that made it unsatisfiable,
+    /// it is empty if the formula is unsatisfiable without assumptions
+    pub fn failed_assumptions(&self) -> &[Lit] {
+        &self.failed
+    }
+
+    /// Compute the assumptions that imply the negation of the given assumption, which is false.
+    /// The reasons are explored backward from the negation along the trail, the decisions met
+    /// are the assumptions it depends on.
+    fn analyze_final(&mut self, lit: Lit) {
+        self.failed.push(lit);
+        if self.working_model.decision_level() == 0 {
+            return;
+        }
+        self.seen[lit.get_var()] = true;
+        let start = self.working_model.trail().len() - self.working_model.trail_above(0).len();
+        for index in (start..self.working_model.trail().len()).rev() {
+            let lit = self.working_model.trail()[index];
+            let var = lit.get_var();
+            if !self.seen[var] {
+                continue;
+            }
+            match self.working_model.reason(var) {
+                // Every decision is an assumption at this point
+                None => self.failed.push(lit),
+                Some(reason) => {
+                    for &lit in &self.clauses[reason][1..] {
+                        if self.working_model.level(lit.get_var()) > 0 {
+                            self.seen[lit.get_var()] = true;
+                        }
+                    }
+                }
+            }
+            self.seen[var] = false;
+        }
+        self.seen[lit.get_var()] = false;
+    }
+
+    /// Pick the next decision variable, randomly for a fraction of the decisions
+    /// and following the variable order otherwise
+    fn pick_branching_var(&mut self) -> Option<Var> {
+        let n = self.seen.len();
+        if n > 0 && self.rng.gen_bool(self.config.random_var_freq) {
+            let var = Var::from_id(self.rng.gen_range(0..n));
+            if self.working_model.eval(Lit::new(var, false)) == BoolValue::Undefined {
+                return Some(var);
+            }
+        }
+        self.order.next(&self.working_model)
+    }
+
+    /// Implement the decision phase of CDCL
+    fn decide(&mut self, var: Var) {
+        let lit = self.phases.decide(var, &self.working_model, &mut self.rng);
+        self.enqueue(lit, None)
+    }
+
+    /// Implement clause propagation using two watched literals.
+    /// Returns a conflicting clause if any.
+    fn propagate(&mut self) -> Option<ClauseRef> {
+        while let Some(lit) = self.working_model.next_to_propagate() {
+            self.budget.propagations += 1;
+            let false_lit = !lit;
+            let mut watchers = std::mem::take(&mut self.watches[false_lit]);
+            let mut conflict = None;
+            let mut kept = 0;
+            let mut i = 0;
+
+            while i < watchers.len() {
+                let watcher = watchers[i];
+                i += 1;
+                if self.working_model.eval(watcher.blocker) == BoolValue::True {
+                    watchers[kept] = watcher;
+                    kept += 1;
+                    continue;
+                }
+
+                // Make sure the false literal is the second one
+                let clause = &mut self.clauses[watcher.clause];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let watcher = Watcher {
+                    clause: watcher.clause,
+                    blocker: first,
+                };
+                if self.working_model.eval(first) == BoolValue::True {
+                    watchers[kept] = watcher;
+                    kept += 1;
+                    continue;
+                }
+
+                // Look for a new literal to watch
+                if let Some(k) = (2..clause.len())
+                    .find(|&k| self.working_model.eval(clause[k]) != BoolValue::False)
+                {
+                    clause.swap(1, k);
+                    self.watches[clause[1]].push(watcher);
+                    continue;
+                }
+
+                // The clause is unit or conflicting
+                watchers[kept] = watcher;
+                kept += 1;
+                if self.working_model.eval(first) == BoolValue::False {
+                    conflict = Some(watcher.clause);
+                    while i < watchers.len() {
+                        watchers[kept] = watchers[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.enqueue(first, Some(watcher.clause));
+                }
+            }
+
+            watchers.truncate(kept);
+            self.watches[false_lit] = watchers;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Implement conflict resolution and clause learning.
+    /// Resolve the conflicting clause with the reasons of the literals of the current level,
+    /// in reverse trail order, until a single one remains: the first unique implication point.
+    /// Returns the level to backtrack to and the learnt clause, whose first literal is the negation
+    /// of the UIP and second literal has the highest level among the others.
+    fn analyze_conflict(&mut self, conflict: ClauseRef) -> (usize, Clause) {
+        let current_level = self.working_model.decision_level();
+        // The first literal is set once the UIP is found
+        let mut learnt = vec![Lit::default()];
+        let mut to_resolve = 0;
+        let mut clause = conflict;
+        let mut index = self.working_model.trail().len();
+
+        loop {
+            if self.clauses.is_learnt(clause) {
+                self.bump_clause(clause);
+            }
+            // The first literal of a reason clause is the one it implied
+            let start = if clause == conflict { 0 } else { 1 };
+            for &lit in &self.clauses[clause][start..] {
+                let var = lit.get_var();
+                let level = self.working_model.level(var);
+                if !self.seen[var] && level > 0 {
+                    self.seen[var] = true;
+                    self.to_bump.push(var);
+                    if level == current_level {
+                        to_resolve += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
                 }
-                self.propagate();
             }
-            if self.working_model.state_formula(&self.clauses) == BoolValue::Undefined {
-                self.level += 1;
-                self.decide();
-                self.propagate();
+
+            // Select the next literal of the current level to resolve on
+            let lit = loop {
+                index -= 1;
+                let lit = self.working_model.trail()[index];
+                if self.seen[lit.get_var()] {
+                    break lit;
+                }
+            };
+            self.seen[lit.get_var()] = false;
+            to_resolve -= 1;
+            if to_resolve == 0 {
+                learnt[0] = !lit;
+                break;
             }
-            if self.working_model.state_formula(&self.clauses) == BoolValue::True {
+            clause = self
+                .working_model
+                .reason(lit.get_var())
+                .expect("a propagated literal has a reason");
+        }
+
+        self.to_clear.clone_from(&learnt);
+        self.minimize(&mut learnt);
+        for lit in self.to_clear.drain(..) {
+            self.seen[lit.get_var()] = false;
+        }
+        if self.config.binary_minimization && self.lbd(&learnt) <= BINARY_MINIMIZATION_LBD {
+            self.binary_minimize(&mut learnt);
+        }
+        self.order.bump(&mut self.to_bump);
+        self.order.decay();
+        self.to_bump.clear();
+        self.clause_increment /= self.config.clause_decay as f32;
+        if self.proof.as_ref().is_some_and(|proof| proof.is_lrat()) {
+            self.lrat_hints(conflict, &learnt);
+        }
+
+        // The level to backtrack to is the second highest level of the learnt clause,
+        // which is the level of its second literal.
+        let lvl = if learnt.len() > 1 {
+            let second = (1..learnt.len())
+                .max_by_key(|&k| self.working_model.level(learnt[k].get_var()))
+                .unwrap();
+            learnt.swap(1, second);
+            self.working_model.level(learnt[1].get_var())
+        } else {
+            0
+        };
+        (lvl, learnt)
+    }
+
+    /// Compute the LRAT hints of the learnt clause: once its literals are false, the units of level 0,
+    /// the reasons of the literals implied from them in trail order, then the conflicting clause,
+    /// are unit in turn until the conflicting one is false.
+    /// They are found by exploring the reasons backward from the conflict until the learnt clause.
+    fn lrat_hints(&mut self, conflict: ClauseRef, learnt: &[Lit]) {
+        let in_learnt: HashSet<Var> = learnt.iter().map(|lit| lit.get_var()).collect();
+        let mut chain = vec![];
+        let mut to_explain = 0;
+        let mut clause = conflict;
+        let mut index = self.working_model.trail().len();
+        loop {
+            for &lit in &self.clauses[clause] {
+                let var = lit.get_var();
+                if self.seen[var] || in_learnt.contains(&var) {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.to_clear.push(lit);
+                if self.working_model.level(var) == 0 {
+                    self.hints.push(self.unit_id[var]);
+                } else {
+                    to_explain += 1;
+                }
+            }
+            if to_explain == 0 {
                 break;
             }
+            let var = loop {
+                index -= 1;
+                let var = self.working_model.trail()[index].get_var();
+                if self.seen[var] && self.working_model.level(var) > 0 {
+                    break var;
+                }
+            };
+            to_explain -= 1;
+            clause = match self.binary_reasons.iter().find(|&&(v, _)| v == var) {
+                Some(&(_, binary)) => binary,
+                None => self
+                    .working_model
+                    .reason(var)
+                    .expect("the literals not in the learnt clause are propagated"),
+            };
+            chain.push(self.clauses.id(clause));
+        }
+        self.hints.extend(chain.iter().rev());
+        self.hints.push(self.clauses.id(conflict));
+        for lit in self.to_clear.drain(..) {
+            self.seen[lit.get_var()] = false;
+        }
+        self.binary_reasons.clear();
+    }
+
+    /// Remove from the learnt clause the literals implied by the other ones.
+    /// The vars of the learnt clause are expected to be seen, and the vars to unmark are added to `to_clear`.
+    fn minimize(&mut self, learnt: &mut Clause) {
+        match self.config.minimization {
+            Minimization::None => {}
+            Minimization::Local => {
+                // A literal is redundant if all the other literals of its reason are in the clause
+                let mut kept = 1;
+                for i in 1..learnt.len() {
+                    let redundant = match self.working_model.reason(learnt[i].get_var()) {
+                        None => false,
+                        Some(reason) => self.clauses[reason][1..].iter().all(|lit| {
+                            self.seen[lit.get_var()] || self.working_model.level(lit.get_var()) == 0
+                        }),
+                    };
+                    if !redundant {
+                        learnt[kept] = learnt[i];
+                        kept += 1;
+                    }
+                }
+                learnt.truncate(kept);
+            }
+            Minimization::Recursive => {
+                // Summarize the levels of the clause to quickly discard the literals that cannot be redundant
+                let levels = learnt[1..]
+                    .iter()
+                    .fold(0, |levels, lit| levels | self.abstract_level(lit.get_var()));
+                let mut kept = 1;
+                for i in 1..learnt.len() {
+                    if self.working_model.reason(learnt[i].get_var()).is_none()
+                        || !self.is_redundant(learnt[i], levels)
+                    {
+                        learnt[kept] = learnt[i];
+                        kept += 1;
+                    }
+                }
+                learnt.truncate(kept);
+            }
+        }
+    }
+
+    /// A bit representing the level of the var, so that sets of levels can be summarized in a single integer
+    #[inline]
+    fn abstract_level(&self, var: Var) -> u32 {
+        1 << (self.working_model.level(var) & 31)
+    }
+
+    /// Whether the (propagated) literal is implied by the literals of the clause being learnt,
+    /// that is to say by the seen literals. It explores the reasons depth first and marks the
+    /// literals found redundant so they are not explored twice.
+    fn is_redundant(&mut self, lit: Lit, levels: u32) -> bool {
+        let mut stack = vec![lit];
+        let top = self.to_clear.len();
+        while let Some(lit) = stack.pop() {
+            let reason = self
+                .working_model
+                .reason(lit.get_var())
+                .expect("only propagated literals are explored");
+            for &lit in &self.clauses[reason][1..] {
+                let var = lit.get_var();
+                if self.seen[var] || self.working_model.level(var) == 0 {
+                    continue;
+                }
+                if self.working_model.reason(var).is_some()
+                    && self.abstract_level(var) & levels != 0
+                {
+                    self.seen[var] = true;
+                    stack.push(lit);
+                    self.to_clear.push(lit);
+                } else {
+                    // Reached a decision or a level absent from the clause, undo the exploration
+                    for lit in self.to_clear.drain(top..) {
+                        self.seen[lit.get_var()] = false;
+                    }
+                    return false;
+                }
+            }
         }
-        self.status = Some(true);
         true
     }
 
-    /// Implement the decision phase of CDCL
-    fn decide(&mut self) {
-        // TODO
-        // use random_unassigned for random variable
-        // and assigns a random bool
-        self.working_model.assign(
-            self.working_model.next_unassigned(),
-            BoolValue::True,
-            self.level,
-        )
+    /// Remove from the learnt clause the literals whose negation is implied by the UIP through a binary clause.
+    /// If (¬uip ∨ x) is a clause and ¬x is in the learnt clause, resolving both removes ¬x.
+    fn binary_minimize(&mut self, learnt: &mut Clause) {
+        for lit in &learnt[1..] {
+            self.seen[lit.get_var()] = true;
+        }
+        // The binary clauses containing the first literal are watched by it, with the other literal as blocker
+        for watcher in &self.watches[learnt[0]] {
+            let other = watcher.blocker;
+            if self.clauses[watcher.clause].len() == 2
+                && self.seen[other.get_var()]
+                && self.working_model.eval(other) == BoolValue::True
+            {
+                self.seen[other.get_var()] = false;
+                if self.proof.as_ref().is_some_and(|proof| proof.is_lrat()) {
+                    self.binary_reasons.push((other.get_var(), watcher.clause));
+                }
+            }
+        }
+        let mut kept = 1;
+        for i in 1..learnt.len() {
+            let var = learnt[i].get_var();
+            if self.seen[var] {
+                self.seen[var] = false;
+                learnt[kept] = learnt[i];
+                kept += 1;
+            }
+        }
+        learnt.truncate(kept);
+    }
+
+    /// The literal block distance of the clause: the number of distinct decision levels of its literals
+    fn lbd(&mut self, clause: &[Lit]) -> u32 {
+        self.lbd_time += 1;
+        let mut lbd = 0;
+        for lit in clause {
+            let level = self.working_model.level(lit.get_var());
+            if self.level_stamp[level] != self.lbd_time {
+                self.level_stamp[level] = self.lbd_time;
+                lbd += 1;
+            }
+        }
+        lbd
+    }
+
+    /// Add the learnt clause, which is unit after backtracking, and assign its first literal
+    fn learn(&mut self, learnt: Clause, lbd: u32) {
+        let id = self.new_id();
+        if let Some(proof) = &mut self.proof {
+            proof.add(id, &learnt, &self.hints);
+            self.hints.clear();
+        }
+        let lit = learnt[0];
+        if learnt.len() > 1 {
+            let cref = self.attach(&learnt, true, lbd, id);
+            self.bump_clause(cref);
+            self.enqueue(lit, Some(cref));
+        } else {
+            self.unit_id[lit.get_var()] = id;
+            self.enqueue(lit, None);
+        }
     }
 
-    // Implement clause propagation
-    fn propagate(&mut self) {
-        let mut something_was_done: bool = true;
+    fn bump_clause(&mut self, cref: ClauseRef) {
+        let activity = self.clauses.activity(cref) + self.clause_increment;
+        self.clauses.set_activity(cref, activity);
+        if activity > CLAUSE_ACTIVITY_LIMIT {
+            for &learnt in &self.learnts {
+                let activity = self.clauses.activity(learnt) / CLAUSE_ACTIVITY_LIMIT;
+                self.clauses.set_activity(learnt, activity);
+            }
+            self.clause_increment /= CLAUSE_ACTIVITY_LIMIT;
+        }
+    }
 
-        while something_was_done {
-            something_was_done = false;
+    /// Whether the clause is the reason of the assignment of its first literal
+    fn is_reason(&self, cref: ClauseRef) -> bool {
+        let lit = self.clauses[cref][0];
+        self.working_model.reason(lit.get_var()) == Some(cref)
+    }
 
-            for clause in self.clauses.clauses.iter() {
-                if let Some(to_be_set_true) = self.working_model.is_unit_clause(clause) {
-                    something_was_done = true;
-                    self.working_model.assign(
-                        to_be_set_true.get_var(),
-                        BoolValue::from(to_be_set_true.is_neg() as i8),
-                        self.level,
-                    );
+    /// Delete the worst half of the learnt clauses, according to their LBD then their activity.
+    /// Glue clauses, whose LBD is low, are kept as well as the reasons of the current assignments.
+    fn reduce_db(&mut self) {
+        self.reduce_interval += self.config.reduce_increment;
+        self.next_reduce = self.conflicts + self.reduce_interval;
 
-                    self.working_model
-                        .add_implications(to_be_set_true.get_var(), clause)
+        let mut candidates: Vec<ClauseRef> = self
+            .learnts
+            .iter()
+            .copied()
+            .filter(|&cref| self.clauses.lbd(cref) > GLUE_LBD && !self.is_reason(cref))
+            .collect();
+        // Worst first
+        candidates.sort_by(|&a, &b| {
+            let clauses = &self.clauses;
+            clauses
+                .lbd(b)
+                .cmp(&clauses.lbd(a))
+                .then(clauses.activity(a).total_cmp(&clauses.activity(b)))
+        });
+        for &cref in &candidates[..candidates.len() / 2] {
+            self.delete_clause(cref);
+        }
+        self.stats.reduced += (candidates.len() / 2) as u64;
+        self.remove_deleted();
+    }
+
+    fn delete_clause(&mut self, cref: ClauseRef) {
+        if let Some(proof) = &mut self.proof {
+            proof.delete(self.clauses.id(cref), &self.clauses[cref]);
+        }
+        self.clauses.free(cref);
+    }
+
+    /// Remove the deleted clauses from the watches and the learnt clauses,
+    /// and compact the arena if they waste too much of it
+    fn remove_deleted(&mut self) {
+        for watchers in self.watches.iter_mut() {
+            watchers.retain(|watcher| !self.clauses.is_deleted(watcher.clause));
+        }
+        self.originals
+            .retain(|&cref| !self.clauses.is_deleted(cref));
+        self.learnts.retain(|&cref| !self.clauses.is_deleted(cref));
+        if self.clauses.wasted() as f64 > self.clauses.size() as f64 * GARBAGE_FRACTION {
+            self.garbage_collect();
+        }
+    }
+
+    /// Remove the learnt clauses subsumed by other clauses and strengthen the learnt clauses
+    /// by self-subsuming resolution, at level 0.
+    /// The reasons of the level 0 assignments are kept as they are.
+    fn subsume_learnts(&mut self) {
+        self.next_subsume = self.conflicts + self.config.subsume_interval;
+        let mut subsumer = Subsumer::new(self.num_vars());
+        let crefs: Vec<ClauseRef> = self
+            .originals
+            .iter()
+            .chain(&self.learnts)
+            .copied()
+            .collect();
+        for &cref in &crefs {
+            let candidate = self.clauses.is_learnt(cref)
+                && self.clauses.id(cref) >= self.subsumed_id
+                && !self.is_reason(cref);
+            subsumer.add(self.clauses[cref].to_vec(), candidate);
+        }
+        self.subsumed_id = self.next_id;
+        // The ids and literals of the clauses as they are strengthened, for the proof
+        let mut ids: Vec<u64> = crefs.iter().map(|&cref| self.clauses.id(cref)).collect();
+        let mut clauses: Vec<Clause> = crefs
+            .iter()
+            .map(|&cref| self.clauses[cref].to_vec())
+            .collect();
+        let mut strengthened = vec![false; crefs.len()];
+        for simplification in subsumer.run() {
+            if let Simplification::Strengthened { clause, by, lit } = simplification {
+                let id = self.new_id();
+                let old = std::mem::take(&mut clauses[clause]);
+                clauses[clause] = old.iter().copied().filter(|&l| l != lit).collect();
+                if let Some(proof) = &mut self.proof {
+                    // The clause `by` propagates the negation of the literal, which falsifies the old clause
+                    proof.add(id, &clauses[clause], &[ids[by], ids[clause]]);
+                    if strengthened[clause] {
+                        proof.delete(ids[clause], &old);
+                    }
                 }
+                ids[clause] = id;
+                strengthened[clause] = true;
+            }
+        }
+
+        for (index, &cref) in crefs.iter().enumerate() {
+            if subsumer.is_removed(index) {
+                self.stats.subsumed += 1;
+            }
+            if subsumer.is_removed(index) || strengthened[index] {
+                self.delete_clause(cref);
             }
         }
+        for index in 0..crefs.len() {
+            if !strengthened[index] {
+                continue;
+            }
+            if subsumer.is_removed(index) {
+                if let Some(proof) = &mut self.proof {
+                    proof.delete(ids[index], &clauses[index]);
+                }
+            } else {
+                self.stats.strengthened += 1;
+                let lbd = self
+                    .clauses
+                    .lbd(crefs[index])
+                    .min(clauses[index].len() as u32);
+                self.add_clause_with_id(std::mem::take(&mut clauses[index]), ids[index], Some(lbd));
+            }
+        }
+        self.remove_deleted();
+    }
+
+    /// Shorten the clauses by vivification, at level 0. The best learnt clauses are tried first,
+    /// then the original ones if asked, until the effort is spent.
+    fn vivify(&mut self) {
+        self.next_vivify = self.conflicts + self.config.vivify_interval;
+        let mut candidates: Vec<ClauseRef> = self
+            .learnts
+            .iter()
+            .copied()
+            .filter(|&cref| !self.is_reason(cref))
+            .collect();
+        // Best first
+        candidates.sort_by(|&a, &b| {
+            let clauses = &self.clauses;
+            clauses
+                .lbd(a)
+                .cmp(&clauses.lbd(b))
+                .then(clauses.activity(b).total_cmp(&clauses.activity(a)))
+        });
+        if self.config.vivify_originals {
+            candidates.extend(self.originals.iter().filter(|&&cref| !self.is_reason(cref)));
+        }
+        let mut effort = 0;
+        for cref in candidates {
+            if effort > VIVIFY_EFFORT {
+                break;
+            }
+            let Some(shorter) = self.vivify_clause(cref, &mut effort) else {
+                continue;
+            };
+            self.stats.vivified += 1;
+            let id = self.new_id();
+            if let Some(proof) = &mut self.proof {
+                proof.add(id, &shorter, &self.hints);
+                self.hints.clear();
+            }
+            self.delete_clause(cref);
+            let lbd = self.clauses.lbd(cref).min(shorter.len() as u32);
+            let learnt = self.clauses.is_learnt(cref).then_some(lbd);
+            let unit = shorter.len() == 1;
+            self.add_clause_with_id(shorter, id, learnt);
+            // The unit has to be propagated at level 0 before the next clause is vivified
+            if unit {
+                break;
+            }
+        }
+        self.remove_deleted();
     }
 
-    // Implement conflict resolution and clause learning
-    fn analyze_conflict(&mut self) -> (i32, Clause) {
-        if let Some(conflict) = self.working_model.conflicting(&self.clauses) {
-            let conflict_clause = self.working_model.find_conflict(&conflict);
-            // find decision level to backtrack to
-            // it is the maximum of all the decision levels in conflict clause - 1
-            let mut max = self.working_model.level(conflict_clause[0].get_var());
-            for lit in &conflict_clause {
-                let current = self.working_model.level(lit.get_var());
-                if current > max {
-                    max = current
+    /// Assign the negations of the literals of the clause in turn, and propagate them without it.
+    /// Once a conflict is met or a literal is implied true, the remaining literals are redundant,
+    /// and the literals implied false are too. Returns the shorter clause, if any.
+    /// Its LRAT hints are those of a clause learnt from the conflict, or from the reason of the implied literal,
+    /// or from the clause itself once all its literals are false.
+    fn vivify_clause(&mut self, cref: ClauseRef, effort: &mut usize) -> Option<Clause> {
+        let lits = self.clauses[cref].to_vec();
+        // Satisfied at level 0
+        if lits
+            .iter()
+            .any(|&lit| self.working_model.eval(lit) == BoolValue::True)
+        {
+            return None;
+        }
+        self.unwatch(cref);
+        self.working_model.new_decision_level();
+        let mut shorter = vec![];
+        let mut conflict = None;
+        for &lit in &lits {
+            match self.working_model.eval(lit) {
+                BoolValue::False => (),
+                BoolValue::True => {
+                    shorter.push(lit);
+                    conflict = self.working_model.reason(lit.get_var());
+                    break;
+                }
+                BoolValue::Undefined => {
+                    shorter.push(lit);
+                    let start = self.working_model.trail().len();
+                    self.enqueue(!lit, None);
+                    conflict = self.propagate();
+                    // The watchers of the false literals are visited
+                    *effort += self.working_model.trail()[start..]
+                        .iter()
+                        .map(|&lit| self.watches[!lit].len())
+                        .sum::<usize>();
+                    if conflict.is_some() {
+                        break;
+                    }
                 }
             }
-            (max as i32 - 1, conflict_clause)
+        }
+        let shortened = shorter.len() < lits.len();
+        if shortened && self.proof.as_ref().is_some_and(|proof| proof.is_lrat()) {
+            self.lrat_hints(conflict.unwrap_or(cref), &shorter);
+        }
+        // The vivification should not change the saved phases
+        let phases: Vec<(Var, BoolValue)> = self
+            .working_model
+            .trail_above(0)
+            .iter()
+            .map(|lit| (lit.get_var(), self.working_model.saved_phase(lit.get_var())))
+            .collect();
+        self.backtrack(0);
+        for (var, phase) in phases {
+            self.working_model.set_saved_phase(var, phase);
+        }
+        if shortened {
+            Some(shorter)
         } else {
-            self::panic!("entered conflict analysis without a conflict")
+            self.watch(cref);
+            None
         }
     }
 
+    /// Compact the clause arena by moving the live clauses to a new one,
+    /// every reference to a clause is updated
+    fn garbage_collect(&mut self) {
+        let mut to = ClauseArena::with_capacity(self.clauses.size() - self.clauses.wasted());
+        let clauses = &mut self.clauses;
+        for watchers in self.watches.iter_mut() {
+            for watcher in watchers.iter_mut() {
+                watcher.clause = clauses.relocate(watcher.clause, &mut to);
+            }
+        }
+        self.working_model
+            .relocate_reasons(|cref| clauses.relocate(cref, &mut to));
+        for cref in self.originals.iter_mut().chain(self.learnts.iter_mut()) {
+            *cref = clauses.relocate(*cref, &mut to);
+        }
+        self.clauses = to;
+        self.stats.collections += 1;
+    }
+
     fn backtrack(&mut self, level: usize) {
-        self.level = level;
+        for lit in self.working_model.trail_above(level) {
+            self.order.unassigned(lit.get_var());
+        }
         self.working_model.backtracking(level);
     }
 }
@@ -0,0 +1,549 @@
+use crate::all_types::*;
+use std::collections::HashMap;
+
+/// Check that the proof shows the unsatisfiability of the formula.
+/// The proof is in LRAT format if the file ends with ".lrat", otherwise it is a DRAT proof
+/// in text or binary format, binary proofs being recognized by their null bytes.
+pub fn check_proof(cnf: &Cnf, path: &str) -> Result<(), String> {
+    let proof = std::fs::read(path).map_err(|e| format!("Impossible to read the proof: {e}"))?;
+    if path.ends_with(".lrat") {
+        let proof = String::from_utf8(proof).map_err(|_| "The LRAT proof is not text")?;
+        LratChecker::new(cnf).check(&proof)
+    } else {
+        let steps = if proof.contains(&0) {
+            parse_binary_drat(&proof)?
+        } else {
+            let proof = String::from_utf8(proof).map_err(|_| "The DRAT proof is not text")?;
+            parse_drat(&proof)?
+        };
+        DratChecker::new(cnf).check(steps)
+    }
+}
+
+/// A step of a DRAT proof, whether it is a deletion and its clause
+type DratStep = (bool, Vec<Lit>);
+
+fn parse_drat(proof: &str) -> Result<Vec<DratStep>, String> {
+    let mut steps = vec![];
+    let mut deletion = false;
+    let mut clause = vec![];
+    for line in proof.lines() {
+        if line.trim_start().starts_with('c') {
+            continue;
+        }
+        for token in line.split_whitespace() {
+            if token == "d" && clause.is_empty() {
+                deletion = true;
+                continue;
+            }
+            match token.parse::<i32>() {
+                Ok(0) => {
+                    steps.push((deletion, std::mem::take(&mut clause)));
+                    deletion = false;
+                }
+                Ok(x) => clause.push(Lit::from(x)),
+                Err(_) => return Err(format!("Unexpected \"{token}\" in the proof")),
+            }
+        }
+    }
+    Ok(steps)
+}
+
+fn parse_binary_drat(proof: &[u8]) -> Result<Vec<DratStep>, String> {
+    let mut steps = vec![];
+    let mut bytes = proof.iter();
+    while let Some(&kind) = bytes.next() {
+        let deletion = match kind {
+            b'a' => false,
+            b'd' => true,
+            _ => return Err(format!("Unexpected byte {kind} in the proof")),
+        };
+        let mut clause = vec![];
+        loop {
+            // 2 * (var + 1) + sign, 7 bits at a time
+            let mut x: u32 = 0;
+            let mut shift = 0;
+            loop {
+                let &byte = bytes
+                    .next()
+                    .ok_or("The proof ends in the middle of a clause")?;
+                // The bits shifted out of the 32 bits are lost
+                let bits = ((byte & 127) as u32)
+                    .checked_shl(shift)
+                    .filter(|bits| bits >> shift == (byte & 127) as u32)
+                    .ok_or("Malformed varint in the binary proof")?;
+                x |= bits;
+                shift += 7;
+                if byte < 128 {
+                    break;
+                }
+            }
+            match x {
+                0 => break,
+                1 => return Err("Unexpected literal 1 in the binary proof".to_string()),
+                _ => clause.push(Lit::new(Var::from_id((x >> 1) as usize - 1), x & 1 == 1)),
+            }
+        }
+        steps.push((deletion, clause));
+    }
+    Ok(steps)
+}
+
+/// The clause in dimacs format, for the error messages
+fn dimacs(clause: &[Lit]) -> String {
+    clause
+        .iter()
+        .map(|&lit| format!("{} ", i32::from(lit)))
+        .collect::<String>()
+        + "0"
+}
+
+/// Backward DRAT checker.
+/// The proof is first replayed to find the empty clause, then the lemmas are checked from the last one,
+/// only if they were used to derive the empty clause or a lemma already checked (the core).
+/// The clauses are propagated with two watched literals, deleted clauses are marked inactive
+/// and skipped by the propagation.
+struct DratChecker {
+    clauses: Vec<Vec<Lit>>,
+    // The first literal of each lemma, on which the RAT property is checked
+    pivots: Vec<Option<Lit>>,
+    active: Vec<bool>,
+    core: Vec<bool>,
+    // The unit clauses, propagated first by each check
+    units: Vec<usize>,
+    watches: Vec<Vec<usize>>,
+    assigns: Vec<BoolValue>,
+    reason: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    seen: Vec<bool>,
+}
+
+/// What was done by a step of the proof, to be undone by the backward check
+enum Done {
+    Added(usize),
+    Deleted(usize),
+}
+
+impl DratChecker {
+    fn new(cnf: &Cnf) -> Self {
+        let mut checker = DratChecker {
+            clauses: vec![],
+            pivots: vec![],
+            active: vec![],
+            core: vec![],
+            units: vec![],
+            watches: vec![],
+            assigns: vec![],
+            reason: vec![],
+            trail: vec![],
+            seen: vec![],
+        };
+        checker.add_vars(cnf.var_num);
+        for clause in &cnf.clauses {
+            checker.add(clause);
+        }
+        checker
+    }
+
+    fn add_vars(&mut self, n: usize) {
+        if n > self.assigns.len() {
+            self.watches.resize(2 * n, vec![]);
+            self.assigns.resize(n, BoolValue::Undefined);
+            self.reason.resize(n, None);
+            self.seen.resize(n, false);
+        }
+    }
+
+    fn add(&mut self, lits: &[Lit]) -> usize {
+        let pivot = lits.first().copied();
+        let mut clause = lits.to_vec();
+        clause.sort();
+        clause.dedup();
+        if let Some(max) = clause.iter().map(|lit| lit.get_var().0 as usize).max() {
+            self.add_vars(max + 1);
+        }
+        let id = self.clauses.len();
+        match clause.len() {
+            0 => {}
+            1 => self.units.push(id),
+            _ => {
+                self.watches[clause[0]].push(id);
+                self.watches[clause[1]].push(id);
+            }
+        }
+        self.clauses.push(clause);
+        self.pivots.push(pivot);
+        self.active.push(true);
+        self.core.push(false);
+        id
+    }
+
+    fn check(&mut self, steps: Vec<DratStep>) -> Result<(), String> {
+        if self.clauses.iter().any(|clause| clause.is_empty()) {
+            return Ok(());
+        }
+        // Replay the proof up to the empty clause, the active clauses are found back by their literals
+        let mut ids: HashMap<Vec<Lit>, Vec<usize>> = HashMap::new();
+        for (id, clause) in self.clauses.iter().enumerate() {
+            ids.entry(clause.clone()).or_default().push(id);
+        }
+        let mut done = vec![];
+        for (deletion, mut clause) in steps {
+            if clause.is_empty() && !deletion {
+                break;
+            }
+            if deletion {
+                clause.sort();
+                clause.dedup();
+                // Like drat-trim, the deletions of unit clauses are ignored
+                if clause.len() == 1 {
+                    continue;
+                }
+                let id = ids
+                    .get_mut(&clause)
+                    .and_then(|ids| ids.pop())
+                    .ok_or_else(|| format!("Deletion of the unknown clause {}", dimacs(&clause)))?;
+                self.active[id] = false;
+                done.push(Done::Deleted(id));
+            } else {
+                let id = self.add(&clause);
+                ids.entry(self.clauses[id].clone()).or_default().push(id);
+                done.push(Done::Added(id));
+            }
+        }
+
+        if !self.rup(&[]) {
+            return Err("The proof does not derive the empty clause".to_string());
+        }
+        for step in done.into_iter().rev() {
+            match step {
+                Done::Deleted(id) => self.active[id] = true,
+                Done::Added(id) => {
+                    self.active[id] = false;
+                    if self.core[id] {
+                        let lemma = self.clauses[id].clone();
+                        if !self.rup(&lemma) && !self.rat(&lemma, self.pivots[id]) {
+                            return Err(format!(
+                                "The lemma {} is neither RUP nor RAT",
+                                dimacs(&lemma)
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        self.assigns[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
+        self.reason[lit.get_var()] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Whether the clause is a reverse unit propagation: assigning its literals to false and
+    /// propagating the active clauses leads to a conflict.
+    /// The clauses used to find the conflict are added to the core.
+    fn rup(&mut self, lemma: &[Lit]) -> bool {
+        let mut conflict = None;
+        let mut tautology = false;
+        for &lit in lemma {
+            match lit.value(&self.assigns) {
+                BoolValue::Undefined => self.assign(!lit, None),
+                BoolValue::True => tautology = true,
+                BoolValue::False => {}
+            }
+        }
+        if !tautology {
+            for i in 0..self.units.len() {
+                let id = self.units[i];
+                if !self.active[id] {
+                    continue;
+                }
+                let lit = self.clauses[id][0];
+                match lit.value(&self.assigns) {
+                    BoolValue::Undefined => self.assign(lit, Some(id)),
+                    BoolValue::False => {
+                        conflict = Some(id);
+                        break;
+                    }
+                    BoolValue::True => {}
+                }
+            }
+            if conflict.is_none() {
+                conflict = self.propagate();
+            }
+            if let Some(id) = conflict {
+                self.mark_core(id);
+            }
+        }
+        for lit in self.trail.drain(..) {
+            self.assigns[lit.get_var()] = BoolValue::Undefined;
+            self.reason[lit.get_var()] = None;
+        }
+        tautology || conflict.is_some()
+    }
+
+    /// Whether the clause is a resolution asymmetric tautology on its pivot: its resolvents with
+    /// all the active clauses containing the negation of the pivot are reverse unit propagations
+    fn rat(&mut self, lemma: &[Lit], pivot: Option<Lit>) -> bool {
+        let Some(pivot) = pivot else {
+            return false;
+        };
+        let candidates: Vec<usize> = (0..self.clauses.len())
+            .filter(|&id| self.active[id] && self.clauses[id].contains(&!pivot))
+            .collect();
+        for id in candidates {
+            let mut resolvent = lemma.to_vec();
+            resolvent.extend(self.clauses[id].iter().filter(|&&lit| lit != !pivot));
+            if !self.rup(&resolvent) {
+                return false;
+            }
+            self.core[id] = true;
+        }
+        true
+    }
+
+    /// Propagate the trail with the active clauses, returns a conflicting clause if any
+    fn propagate(&mut self) -> Option<usize> {
+        let mut head = 0;
+        while head < self.trail.len() {
+            let false_lit = !self.trail[head];
+            head += 1;
+            let mut i = 0;
+            while i < self.watches[false_lit].len() {
+                let id = self.watches[false_lit][i];
+                if !self.active[id] {
+                    i += 1;
+                    continue;
+                }
+                let assigns = &self.assigns;
+                let clause = &mut self.clauses[id];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if first.value(assigns) == BoolValue::True {
+                    i += 1;
+                    continue;
+                }
+                if let Some(k) =
+                    (2..clause.len()).find(|&k| clause[k].value(assigns) != BoolValue::False)
+                {
+                    clause.swap(1, k);
+                    let lit = clause[1];
+                    self.watches[false_lit].swap_remove(i);
+                    self.watches[lit].push(id);
+                    continue;
+                }
+                if first.value(assigns) == BoolValue::False {
+                    return Some(id);
+                }
+                self.assign(first, Some(id));
+                i += 1;
+            }
+        }
+        None
+    }
+
+    /// Add to the core the conflicting clause and the reasons it depends on
+    fn mark_core(&mut self, conflict: usize) {
+        self.core[conflict] = true;
+        for lit in &self.clauses[conflict] {
+            self.seen[lit.get_var()] = true;
+        }
+        for &lit in self.trail.iter().rev() {
+            let var = lit.get_var();
+            if !self.seen[var] {
+                continue;
+            }
+            if let Some(reason) = self.reason[var] {
+                self.core[reason] = true;
+                for lit in &self.clauses[reason] {
+                    self.seen[lit.get_var()] = true;
+                }
+            }
+            self.seen[var] = false;
+        }
+    }
+}
+
+/// Forward LRAT checker.
+/// Each lemma comes with the clauses (hints) that become unit in order once its literals are false,
+/// the last one being falsified. For RAT lemmas, each clause containing the negation of the pivot
+/// is given by its negated id followed by the hints of the resolvent.
+struct LratChecker {
+    clauses: HashMap<u64, Vec<Lit>>,
+    assigns: Vec<BoolValue>,
+    trail: Vec<Lit>,
+}
+
+impl LratChecker {
+    fn new(cnf: &Cnf) -> Self {
+        LratChecker {
+            clauses: (1..)
+                .zip(cnf.clauses.iter())
+                .map(|(id, clause)| (id, clause.clone()))
+                .collect(),
+            assigns: vec![BoolValue::Undefined; cnf.var_num],
+            trail: vec![],
+        }
+    }
+
+    fn check(&mut self, proof: &str) -> Result<(), String> {
+        if self.clauses.values().any(|clause| clause.is_empty()) {
+            return Ok(());
+        }
+        for line in proof.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() || tokens[0] == "c" {
+                continue;
+            }
+            let numbers = |tokens: &[&str]| -> Result<Vec<i64>, String> {
+                tokens
+                    .iter()
+                    .map(|token| {
+                        token
+                            .parse::<i64>()
+                            .map_err(|_| format!("Unexpected \"{token}\" in the proof"))
+                    })
+                    .collect()
+            };
+            let id = |x: i64| {
+                u64::try_from(x)
+                    .ok()
+                    .filter(|&id| id > 0)
+                    .ok_or_else(|| format!("Invalid clause id {x} in the proof"))
+            };
+            if tokens.get(1) == Some(&"d") {
+                for x in numbers(&tokens[2..])? {
+                    // The list of deleted ids ends with a 0
+                    if x != 0 {
+                        self.clauses.remove(&id(x)?);
+                    }
+                }
+                continue;
+            }
+            let numbers = numbers(&tokens)?;
+            let id = id(numbers[0])?;
+            let end = 1 + numbers[1..]
+                .iter()
+                .position(|&x| x == 0)
+                .ok_or_else(|| format!("The lemma {id} is not ended by a 0"))?;
+            let lemma: Vec<Lit> = numbers[1..end]
+                .iter()
+                .map(|&x| {
+                    i32::try_from(x)
+                        .map(Lit::from)
+                        .map_err(|_| format!("Invalid literal {x} in the lemma {id}"))
+                })
+                .collect::<Result<_, _>>()?;
+            let hints = numbers[end + 1..]
+                .strip_suffix(&[0])
+                .unwrap_or(&numbers[end + 1..]);
+            if let Some(max) = lemma.iter().map(|lit| lit.get_var().0 as usize).max() {
+                if max >= self.assigns.len() {
+                    self.assigns.resize(max + 1, BoolValue::Undefined);
+                }
+            }
+
+            let result = self.check_lemma(&lemma, hints);
+            for lit in self.trail.drain(..) {
+                self.assigns[lit.get_var()] = BoolValue::Undefined;
+            }
+            result.map_err(|e| format!("Lemma {id}: {e}"))?;
+            if lemma.is_empty() {
+                return Ok(());
+            }
+            self.clauses.insert(id, lemma);
+        }
+        Err("The proof does not derive the empty clause".to_string())
+    }
+
+    fn check_lemma(&mut self, lemma: &[Lit], hints: &[i64]) -> Result<(), String> {
+        for &lit in lemma {
+            match lit.value(&self.assigns) {
+                BoolValue::Undefined => self.assign(!lit),
+                // Tautology
+                BoolValue::True => return Ok(()),
+                BoolValue::False => {}
+            }
+        }
+        let rup_end = hints.iter().position(|&h| h < 0).unwrap_or(hints.len());
+        if self.propagate_hints(&hints[..rup_end])? {
+            return Ok(());
+        }
+
+        // RAT on the first literal
+        let pivot = *lemma.first().ok_or("no conflict found")?;
+        let mut groups: HashMap<u64, &[i64]> = HashMap::new();
+        let mut rest = &hints[rup_end..];
+        while let Some((&first, tail)) = rest.split_first() {
+            let end = tail.iter().position(|&h| h < 0).unwrap_or(tail.len());
+            groups.insert(first.unsigned_abs(), &tail[..end]);
+            rest = &tail[end..];
+        }
+        let mut candidates: Vec<(u64, Vec<Lit>)> = self
+            .clauses
+            .iter()
+            .filter(|(_, clause)| clause.contains(&!pivot))
+            .map(|(&id, clause)| (id, clause.clone()))
+            .collect();
+        candidates.sort_unstable_by_key(|&(id, _)| id);
+        for (id, clause) in candidates {
+            let start = self.trail.len();
+            let mut satisfied = false;
+            for &lit in clause.iter().filter(|&&lit| lit != !pivot) {
+                match lit.value(&self.assigns) {
+                    BoolValue::Undefined => self.assign(!lit),
+                    BoolValue::True => satisfied = true,
+                    BoolValue::False => {}
+                }
+            }
+            if !satisfied {
+                let hints = groups.get(&id).ok_or_else(|| {
+                    if groups.is_empty() {
+                        "no conflict found with the hints".to_string()
+                    } else {
+                        format!("no hints for the RAT candidate {id}")
+                    }
+                })?;
+                if !self.propagate_hints(hints)? {
+                    return Err(format!("no conflict found with the RAT candidate {id}"));
+                }
+            }
+            for lit in self.trail.drain(start..) {
+                self.assigns[lit.get_var()] = BoolValue::Undefined;
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, lit: Lit) {
+        self.assigns[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
+        self.trail.push(lit);
+    }
+
+    /// Assign the unit literal of each hint in order, returns whether a hint is falsified
+    fn propagate_hints(&mut self, hints: &[i64]) -> Result<bool, String> {
+        for &hint in hints {
+            let clause = u64::try_from(hint)
+                .ok()
+                .and_then(|id| self.clauses.get(&id))
+                .ok_or_else(|| format!("unknown hint {hint}"))?;
+            let mut unit = None;
+            for &lit in clause {
+                match lit.value(&self.assigns) {
+                    BoolValue::False => {}
+                    BoolValue::Undefined if unit.is_none() || unit == Some(lit) => unit = Some(lit),
+                    _ => return Err(format!("the hint {hint} is not unit")),
+                }
+            }
+            match unit {
+                None => return Ok(true),
+                Some(lit) => self.assign(lit),
+            }
+        }
+        Ok(false)
+    }
+}
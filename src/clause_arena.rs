@@ -0,0 +1,122 @@
+use crate::all_types::*;
+use std::ops::{Index, IndexMut};
+
+// Each clause is stored as a header followed by its literals:
+// [size] [flags] [activity or new position] [id low bits] [id high bits] [lit 0] [lit 1] ...
+const HEADER_SIZE: usize = 5;
+const LEARNT: u32 = 1;
+const DELETED: u32 = 1 << 1;
+const RELOCATED: u32 = 1 << 2;
+// The LBD is stored in the flags above the three bits
+const LBD_SHIFT: u32 = 3;
+
+/// The clauses of the CDCL solver stored contiguously in a single vector, to be cache friendly.
+/// A clause is addressed by the `ClauseRef` of its header, which stores its size, whether it is learnt
+/// or deleted, its LBD, its activity and its id in the proofs.
+/// Deleted clauses keep wasting space until the arena is compacted by moving the live clauses
+/// to a new arena with `relocate`.
+#[derive(Debug, Default)]
+pub struct ClauseArena {
+    data: Vec<u32>,
+    wasted: usize,
+}
+
+impl ClauseArena {
+    pub fn with_capacity(capacity: usize) -> Self {
+        ClauseArena {
+            data: Vec::with_capacity(capacity),
+            wasted: 0,
+        }
+    }
+
+    pub fn alloc(&mut self, lits: &[Lit], learnt: bool, lbd: u32, id: u64) -> ClauseRef {
+        let cref = ClauseRef(self.data.len() as u32);
+        self.data.push(lits.len() as u32);
+        self.data
+            .push((lbd << LBD_SHIFT) | if learnt { LEARNT } else { 0 });
+        self.data.push(0f32.to_bits());
+        self.data.push(id as u32);
+        self.data.push((id >> 32) as u32);
+        self.data.extend(lits.iter().map(|&lit| lit.code()));
+        cref
+    }
+
+    /// The number of words used, including the wasted ones
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+    /// The number of words used by deleted clauses
+    pub fn wasted(&self) -> usize {
+        self.wasted
+    }
+
+    #[inline]
+    fn flags(&self, cref: ClauseRef) -> u32 {
+        self.data[cref.0 as usize + 1]
+    }
+    #[inline]
+    pub fn is_learnt(&self, cref: ClauseRef) -> bool {
+        self.flags(cref) & LEARNT != 0
+    }
+    #[inline]
+    pub fn is_deleted(&self, cref: ClauseRef) -> bool {
+        self.flags(cref) & DELETED != 0
+    }
+    #[inline]
+    pub fn lbd(&self, cref: ClauseRef) -> u32 {
+        self.flags(cref) >> LBD_SHIFT
+    }
+    #[inline]
+    pub fn activity(&self, cref: ClauseRef) -> f32 {
+        f32::from_bits(self.data[cref.0 as usize + 2])
+    }
+    pub fn set_activity(&mut self, cref: ClauseRef, activity: f32) {
+        self.data[cref.0 as usize + 2] = activity.to_bits();
+    }
+    #[inline]
+    pub fn id(&self, cref: ClauseRef) -> u64 {
+        self.data[cref.0 as usize + 3] as u64 | (self.data[cref.0 as usize + 4] as u64) << 32
+    }
+
+    /// Mark the clause as deleted, its space is reclaimed by the next compaction
+    pub fn free(&mut self, cref: ClauseRef) {
+        self.data[cref.0 as usize + 1] |= DELETED;
+        self.wasted += HEADER_SIZE + self[cref].len();
+    }
+
+    /// Copy the clause to the other arena and return its new reference.
+    /// The new reference is remembered, so relocating the same clause again returns it.
+    pub fn relocate(&mut self, cref: ClauseRef, to: &mut ClauseArena) -> ClauseRef {
+        let start = cref.0 as usize;
+        if self.data[start + 1] & RELOCATED != 0 {
+            return ClauseRef(self.data[start + 2]);
+        }
+        let end = start + HEADER_SIZE + self.data[start] as usize;
+        let new = ClauseRef(to.data.len() as u32);
+        to.data.extend_from_slice(&self.data[start..end]);
+        self.data[start + 1] |= RELOCATED;
+        self.data[start + 2] = new.0;
+        new
+    }
+}
+
+impl Index<ClauseRef> for ClauseArena {
+    type Output = [Lit];
+    #[inline]
+    fn index(&self, cref: ClauseRef) -> &Self::Output {
+        let start = cref.0 as usize + HEADER_SIZE;
+        let words = &self.data[start..start + self.data[cref.0 as usize] as usize];
+        // SAFETY: `Lit` is a transparent wrapper around `u32`
+        unsafe { std::slice::from_raw_parts(words.as_ptr() as *const Lit, words.len()) }
+    }
+}
+impl IndexMut<ClauseRef> for ClauseArena {
+    #[inline]
+    fn index_mut(&mut self, cref: ClauseRef) -> &mut Self::Output {
+        let start = cref.0 as usize + HEADER_SIZE;
+        let len = self.data[cref.0 as usize] as usize;
+        let words = &mut self.data[start..start + len];
+        // SAFETY: `Lit` is a transparent wrapper around `u32`
+        unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut Lit, words.len()) }
+    }
+}
@@ -0,0 +1,105 @@
+/// The heuristic used to pick the next decision variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branching {
+    /// Chaff's VSIDS: bump the activity of the variables met in conflicts
+    /// and periodically multiply every activity by the decay factor.
+    Vsids,
+    /// MiniSat's exponential VSIDS: the bump grows after each conflict instead of decaying every activity.
+    Evsids,
+    /// Variable move to front: the most recently bumped unassigned variable is picked.
+    Vmtf,
+}
+
+/// The value given to a decision variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    False,
+    True,
+    /// The value the variable had before it was last unassigned (phase saving)
+    Saved,
+    Random,
+    /// The value the variable had in the longest conflict free assignment,
+    /// the phases are periodically reset (rephasing) to escape from it
+    Target,
+}
+
+/// When the CDCL solver restarts, that is to say backtracks to level 0 while keeping what it learnt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Restart {
+    Never,
+    /// The i-th restart happens after luby(i) * unit conflicts, where luby is the sequence 1 1 2 1 1 2 4 ...
+    Luby {
+        unit: u64,
+    },
+    /// The i-th restart happens after first * factor^i conflicts
+    Geometric {
+        first: u64,
+        factor: f64,
+    },
+    /// Glucose's dynamic restarts: restart when the average LBD of the last `window` learnt clauses
+    /// times `margin` is above the average LBD of all the learnt clauses
+    Glucose {
+        window: usize,
+        margin: f64,
+    },
+}
+
+/// How the learnt clauses are shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Minimization {
+    None,
+    /// Remove the literals whose reason only contains literals of the clause
+    Local,
+    /// Remove the literals implied by the literals of the clause, exploring the reasons recursively
+    Recursive,
+}
+
+/// The options of the CDCL solver.
+#[derive(Debug, Clone)]
+pub struct CdclConfig {
+    pub branching: Branching,
+    pub polarity: Polarity,
+    pub restart: Restart,
+    pub minimization: Minimization,
+    /// Also remove from the learnt clauses with a low LBD the literals implied by the UIP through binary clauses
+    pub binary_minimization: bool,
+    /// The decay factor of the activities, the lower the faster old conflicts are forgotten
+    pub var_decay: f64,
+    /// The decay factor of the activities of the learnt clauses
+    pub clause_decay: f64,
+    /// The number of conflicts before the first reduction of the learnt clauses
+    pub reduce_interval: u64,
+    /// How much the number of conflicts between two reductions grows after each one
+    pub reduce_increment: u64,
+    /// The number of conflicts between two simplifications of the learnt clauses by subsumption, 0 to never run them
+    pub subsume_interval: u64,
+    /// The number of conflicts between two vivifications of the learnt clauses, 0 to never run them
+    pub vivify_interval: u64,
+    /// Also vivify the original clauses
+    pub vivify_originals: bool,
+    /// The fraction of the decisions made on a random variable
+    pub random_var_freq: f64,
+    /// The seed of the random choices
+    pub seed: u64,
+}
+
+impl Default for CdclConfig {
+    fn default() -> Self {
+        CdclConfig {
+            branching: Branching::Evsids,
+            polarity: Polarity::Saved,
+            restart: Restart::Luby { unit: 100 },
+            minimization: Minimization::Recursive,
+            binary_minimization: true,
+            var_decay: 0.95,
+            clause_decay: 0.999,
+            reduce_interval: 2000,
+            reduce_increment: 300,
+            subsume_interval: 10000,
+            vivify_interval: 3000,
+            vivify_originals: false,
+            random_var_freq: 0.,
+            seed: 0,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::solver::{Budget, Limits, SolveResult, Terminator};
 use crate::*;
 
 /// The DPLL algorithm is a bit better than the naive one.
@@ -6,6 +7,7 @@ pub struct Dpll {
     n: usize,
     clauses: Vec<Clause>,
     pub assigns: Vec<BoolValue>,
+    budget: Budget,
 }
 
 impl<'a> solver::Solver<'a> for Dpll {
@@ -17,6 +19,7 @@ impl<'a> solver::Solver<'a> for Dpll {
             n: cnf.var_num,
             clauses: cnf.clauses.clone(),
             assigns: vec![BoolValue::Undefined; cnf.var_num],
+            budget: Budget::default(),
         }
     }
 
@@ -24,31 +27,50 @@ impl<'a> solver::Solver<'a> for Dpll {
         &self.assigns
     }
 
-    fn solve(&mut self) -> bool {
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    fn solve(&mut self) -> SolveResult {
+        self.budget.reset();
         let clauses = self.clauses.clone();
         self.dpll(&clauses)
     }
 }
 
 impl Dpll {
-    fn dpll(&mut self, formula: &[Clause]) -> bool {
+    fn dpll(&mut self, formula: &[Clause]) -> SolveResult {
         let mut formula = formula.to_owned();
 
         self.unit_propagation(&mut formula);
 
         if formula.is_empty() {
-            return true;
+            return SolveResult::Sat;
         }
 
         if formula.iter().any(|clause| clause.is_empty()) {
-            return false;
+            return SolveResult::Unsat;
         }
 
+        // Each branch counts as a decision
+        self.budget.decisions += 1;
+        if self.budget.exhausted() {
+            return SolveResult::Unknown;
+        }
         let var = self.next_unassigned(&formula);
 
         formula.push(vec![Lit::from(var as i32 + 1)]);
-        if self.dpll(&formula) {
-            return true;
+        let result = self.dpll(&formula);
+        if result != SolveResult::Unsat {
+            return result;
         }
 
         formula.pop();
@@ -59,6 +81,7 @@ impl Dpll {
     fn unit_propagation(&mut self, formula: &mut Vec<Clause>) {
         while let Some(clause) = formula.iter().find(|clause| clause.len() == 1) {
             let lit = clause[0];
+            self.budget.propagations += 1;
             self.assigns[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
             self.remove_useless(formula, lit);
         }
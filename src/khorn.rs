@@ -1,4 +1,5 @@
 use crate::all_types::*;
+use crate::solver::{Budget, Limits, SolveResult, Terminator};
 use std::collections::hash_set::HashSet;
 
 /// A solver for Horn formulae.
@@ -11,6 +12,7 @@ pub struct KhornSolver<'a> {
     status: Option<bool>,
     assigned_pos: HashSet<Var>,
     assigns: Vec<BoolValue>,
+    budget: Budget,
 }
 impl<'a> crate::solver::Solver<'a> for KhornSolver<'a> {
     fn new<'b: 'a>(formula: &'b Cnf) -> Self {
@@ -33,12 +35,26 @@ impl<'a> crate::solver::Solver<'a> for KhornSolver<'a> {
             formula: CAllClauses::new(new_clauses),
             assigned_pos: HashSet::new(),
             assigns: vec![BoolValue::False; formula.var_num],
+            budget: Budget::default(),
         }
     }
 
-    fn solve(&mut self) -> bool {
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    fn solve(&mut self) -> SolveResult {
+        self.budget.reset();
         if let Some(status) = self.status {
-            status
+            SolveResult::from(status)
         } else {
             self.linear_solve()
         }
@@ -52,7 +68,7 @@ impl<'a> crate::solver::Solver<'a> for KhornSolver<'a> {
     }
 }
 impl<'a> KhornSolver<'a> {
-    fn linear_solve(&mut self) -> bool {
+    fn linear_solve(&mut self) -> SolveResult {
         // ind(clause) = self.formula.clauses.position(clause)
         let mut score: Vec<u32> = vec![0; self.num_clauses]; // ind(clause) -> score
         let mut clauses_with_negvar: Vec<HashSet<u32>> = vec![HashSet::new(); self.num_var]; // var -> list[ind(clause)]
@@ -76,12 +92,17 @@ impl<'a> KhornSolver<'a> {
             let curr_clause = &self.formula.clauses[curr as usize];
             let opt_v = curr_clause.pos;
             if opt_v.is_none() {
-                return false;
+                return SolveResult::Unsat;
             }
             let v = opt_v.unwrap();
             if self.assigned_pos.contains(&v) || clauses_with_negvar[v].contains(&curr) {
                 continue;
             }
+            // Setting a var to true propagates it
+            self.budget.propagations += 1;
+            if self.budget.exhausted() {
+                return SolveResult::Unknown;
+            }
             self.assigned_pos.insert(v);
             for &c in clauses_with_negvar[v].iter() {
                 pool[score[c as usize] as usize].remove(&c);
@@ -89,7 +110,7 @@ impl<'a> KhornSolver<'a> {
                 pool[score[c as usize] as usize].insert(c);
             }
         }
-        true
+        SolveResult::Sat
     }
 }
 
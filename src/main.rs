@@ -1,76 +1,295 @@
 mod all_types;
+mod branching;
+mod checker;
+mod clause_arena;
+mod config;
+mod dpll;
 mod khorn;
 mod parser;
+mod phases;
+mod preprocess;
+mod proof;
+mod reconstruction;
+mod restart;
 mod sat2;
 mod solver;
 mod cdcl;
+mod subsumption;
 mod tautosolver;
 use core::panic;
+use std::io::Write;
 
 use crate::all_types::*;
 use crate::solver::*;
 
-fn get_args(args: Vec<String>) -> (Vec<String>, Vec<String>) {
+/// The arguments expecting a value right after them
+const VALUE_ARGS: [&str; 15] = [
+    "-t",
+    "--time",
+    "--conflicts",
+    "--propagations",
+    "--decisions",
+    "--branching",
+    "--polarity",
+    "--restart",
+    "--minimize",
+    "--random-freq",
+    "--assume",
+    "--drat",
+    "--lrat",
+    "--check-proof",
+    "--output",
+];
+
+fn get_args(args: Vec<String>) -> (Vec<String>, Vec<(String, String)>, Vec<String>) {
     let mut flags = vec![];
+    let mut options = vec![];
     let mut files = vec![];
     let mut i = 1;
     while i < args.len() {
-        if args[i].starts_with('-') {
-            if args[i] == "-t" || args[i] == "--time" {
-                i += 1;
-                if i == args.len() {
-                    eprintln!("Expected a time after the \"--time\" of \"-t\" argument");
+        if VALUE_ARGS.contains(&args[i].as_str()) {
+            if i + 1 == args.len() {
+                eprintln!("Expected a value after the \"{}\" argument", args[i]);
+                std::process::exit(6);
+            }
+            options.push((args[i].to_string(), args[i + 1].to_string()));
+            i += 1;
+        } else if args[i].starts_with('-') && args[i] != "-" {
+            flags.push(args[i].to_string());
+        } else {
+            files.push(args[i].to_string());
+        }
+
+        i += 1;
+    }
+    (flags, options, files)
+}
+
+/// Build the configuration of the CDCL solver from the command line options
+fn cdcl_config(options: &[(String, String)]) -> config::CdclConfig {
+    let mut config = config::CdclConfig::default();
+    for (option, value) in options {
+        if option == "--branching" {
+            config.branching = match value.as_str() {
+                "vsids" => config::Branching::Vsids,
+                "evsids" => config::Branching::Evsids,
+                "vmtf" => config::Branching::Vmtf,
+                _ => {
+                    eprintln!("Expected \"vsids\", \"evsids\" or \"vmtf\" after \"--branching\", got {value}");
                     std::process::exit(6);
-                } else if args[i].parse::<u8>().is_ok() {
-                    flags.push(args[i].to_string());
-                } else {
+                }
+            }
+        } else if option == "--polarity" {
+            config.polarity = match value.as_str() {
+                "false" => config::Polarity::False,
+                "true" => config::Polarity::True,
+                "saved" => config::Polarity::Saved,
+                "random" => config::Polarity::Random,
+                "target" => config::Polarity::Target,
+                _ => {
+                    eprintln!("Expected \"false\", \"true\", \"saved\", \"random\" or \"target\" after \"--polarity\", got {value}");
+                    std::process::exit(6);
+                }
+            }
+        } else if option == "--restart" {
+            config.restart = match value.as_str() {
+                "never" => config::Restart::Never,
+                "luby" => config::Restart::Luby { unit: 100 },
+                "geometric" => config::Restart::Geometric {
+                    first: 100,
+                    factor: 1.5,
+                },
+                "glucose" => config::Restart::Glucose {
+                    window: 50,
+                    margin: 0.8,
+                },
+                _ => {
+                    eprintln!("Expected \"never\", \"luby\", \"geometric\" or \"glucose\" after \"--restart\", got {value}");
+                    std::process::exit(6);
+                }
+            }
+        } else if option == "--minimize" {
+            config.minimization = match value.as_str() {
+                "none" => config::Minimization::None,
+                "local" => config::Minimization::Local,
+                "recursive" => config::Minimization::Recursive,
+                _ => {
+                    eprintln!("Expected \"none\", \"local\" or \"recursive\" after \"--minimize\", got {value}");
+                    std::process::exit(6);
+                }
+            }
+        } else if option == "--random-freq" {
+            config.random_var_freq = match value.parse::<f64>() {
+                Ok(freq) if (0. ..=1.).contains(&freq) => freq,
+                _ => {
                     eprintln!(
-                        "Expected a number after the \"--time\" of \"-t\" argument, got {}",
-                        args[i]
+                        "Expected a number between 0 and 1 after \"--random-freq\", got {value}"
                     );
                     std::process::exit(6);
                 }
             }
-            flags.push(args[i].to_string());
-        } else {
-            files.push(args[i].to_string());
         }
+    }
+    config
+}
 
-        i += 1;
+/// The resources the solvers may use on each formula
+fn limits(options: &[(String, String)]) -> Limits {
+    let mut limits = Limits::default();
+    for (option, value) in options {
+        if option == "-t" || option == "--time" {
+            match value.parse::<f64>() {
+                Ok(seconds) if seconds >= 0. => {
+                    limits.time = Some(std::time::Duration::from_secs_f64(seconds))
+                }
+                _ => {
+                    eprintln!("Expected a number of seconds after \"{option}\", got {value}");
+                    std::process::exit(6);
+                }
+            }
+        } else if ["--conflicts", "--propagations", "--decisions"].contains(&option.as_str()) {
+            let Ok(limit) = value.parse::<u64>() else {
+                eprintln!("Expected a non negative integer after \"{option}\", got {value}");
+                std::process::exit(6);
+            };
+            match option.as_str() {
+                "--conflicts" => limits.conflicts = Some(limit),
+                "--propagations" => limits.propagations = Some(limit),
+                _ => limits.decisions = Some(limit),
+            }
+        }
     }
-    (flags, files)
+    limits
 }
 
-fn get_cnfs(files: Vec<String>, verbose: bool) -> Vec<Cnf> {
+/// The literals assumed by the CDCL solver, given in dimacs format and separated by spaces or commas
+fn assumptions(options: &[(String, String)]) -> Vec<Lit> {
+    let mut assumptions = vec![];
+    for (option, value) in options {
+        if option == "--assume" {
+            for x in value.split([' ', ',']).filter(|x| !x.is_empty()) {
+                match x.parse::<i32>() {
+                    Ok(x) if x != 0 => assumptions.push(Lit::from(x)),
+                    _ => {
+                        eprintln!("Expected non zero literals after \"--assume\", got {value}");
+                        std::process::exit(6);
+                    }
+                }
+            }
+        }
+    }
+    assumptions
+}
+
+fn get_cnfs(files: Vec<String>, mode: parser::ParseMode, verbose: bool) -> Vec<Cnf> {
     let mut cnfs: Vec<Cnf> = Vec::new();
     for file in files {
-        let cnf: crate::Cnf = parser::parse_cnf(&file, verbose).unwrap();
-        cnfs.push(cnf);
+        match parser::parse_cnf(&file, mode, verbose) {
+            Ok(cnf) => cnfs.push(cnf),
+            Err(e) => {
+                eprintln!("Impossible to parse the file {file}: {e}");
+                std::process::exit(6);
+            }
+        }
     }
     cnfs
 }
 
 fn help() {
     println!("This function imlements different SAT solvers.");
-    println!("To use it, you may write `sat_solver [args] file(s)`");
+    println!(
+        "To use it, you may write `sat_solver [args] file(s)`, where \"-\" is the standard input."
+    );
     println!("Default is an optimization that determines which solver to use.");
     println!();
     println!("-h --help     Show this message");
     println!("--cdcl        Using the CDCL solver");
     println!("--khorn       Using the Khorn solver");
     println!("--dummy       Using the dummy solver");
+    println!("--dpll        Using the DPLL solver");
     println!("--2sat        Using the 2sat solver");
     println!("--proof       Test whether the returned assigments are correct (the ouput model indeed satisfies the problem)");
     println!("-v --verbose  Print the model and different informations");
+    println!("--branching <vsids|evsids|vmtf>  The decision heuristic of the CDCL solver (default evsids)");
+    println!("--polarity <false|true|saved|random|target>  The value given to the decisions of the CDCL solver (default saved)");
+    println!(
+        "--restart <never|luby|geometric|glucose>  When the CDCL solver restarts (default luby)"
+    );
+    println!("--minimize <none|local|recursive>  How the CDCL solver shortens learnt clauses (default recursive)");
+    println!("--random-freq <f>                The fraction of random decisions of the CDCL solver (default 0)");
+    println!("--assume <lits>                  Solve with the CDCL solver assuming the given literals, for example \"1 -3\"");
+    println!("--drat <file>                    Write a DRAT proof of the CDCL solver to the file");
+    println!("--binary-drat                    Write the DRAT proof in binary format");
+    println!("--lrat <file>                    Write a LRAT proof of the CDCL solver to the file");
+    println!("--no-preprocess                  Do not simplify the formula (probing, equivalent literals, subsumption, variable and blocked clause elimination) before running the CDCL solver");
+    println!(
+        "-t --time <seconds>              Give up on a formula after this time, and answer UNKNOWN"
+    );
+    println!("--conflicts <n>                  Give up after this number of conflicts");
+    println!("--propagations <n>               Give up after this number of propagated literals");
+    println!("--decisions <n>                  Give up after this number of decisions");
+    println!("--output <file>                  Write the answers and the models to the file in the SAT competition format");
+    println!("--strict                         Reject the files which do not follow the DIMACS format exactly");
+    println!("--check-proof <file>             Check the DRAT or LRAT (.lrat) proof of unsatisfiability of the file instead of solving it");
+    println!("The exit code is 10, 20 or 0 when the last formula is satisfiable, unsatisfiable or unknown.");
     println!("For example `./sat_solver --cdcl tests/sat/horn1.cnf tests/unsat/php6-4.cnf` will returns :");
     println!("SAT\nUNSAT");
 }
 
-fn print_status(is_sat: bool) {
-    if is_sat {
-        println!("s \x1b[32mSATISFIABLE\x1b[0m")
-    } else {
-        println!("s \x1b[31mUNSATISFIABLE\x1b[0m")
+/// Print the resources used by the solver, as comments
+fn print_stats(budget: &Budget) {
+    println!("c conflicts: {}", budget.conflicts);
+    println!("c propagations: {}", budget.propagations);
+    println!("c decisions: {}", budget.decisions);
+    println!("c time: {} seconds", budget.elapsed().as_secs_f64());
+}
+
+/// The exit code given to the answer in the SAT competitions
+fn exit_code(result: SolveResult) -> i32 {
+    match result {
+        SolveResult::Sat => 10,
+        SolveResult::Unsat => 20,
+        SolveResult::Unknown => 0,
+    }
+}
+
+/// Write the answer in the SAT competition format, the status line then the model on "v" lines ended by a 0
+fn write_answer(
+    output: &mut impl std::io::Write,
+    result: SolveResult,
+    model: &[BoolValue],
+    var_num: usize,
+) -> std::io::Result<()> {
+    match result {
+        SolveResult::Sat => writeln!(output, "s SATISFIABLE")?,
+        SolveResult::Unsat => writeln!(output, "s UNSATISFIABLE")?,
+        SolveResult::Unknown => writeln!(output, "s UNKNOWN")?,
+    }
+    if result == SolveResult::Sat {
+        // The vars the solver did not assign, as they occur in no clause, are given true
+        let mut model = model.to_vec();
+        model.resize(model.len().max(var_num), BoolValue::Undefined);
+        for (line, values) in model.chunks(10).enumerate() {
+            write!(output, "v")?;
+            for (var, value) in values.iter().enumerate() {
+                let lit = (line * 10 + var + 1) as i32;
+                match value {
+                    BoolValue::False => write!(output, " {}", -lit)?,
+                    _ => write!(output, " {lit}")?,
+                }
+            }
+            writeln!(output)?;
+        }
+        writeln!(output, "v 0")?;
+    }
+    Ok(())
+}
+
+fn print_status(result: SolveResult) {
+    match result {
+        SolveResult::Sat => println!("s \x1b[32mSATISFIABLE\x1b[0m"),
+        SolveResult::Unsat => println!("s \x1b[31mUNSATISFIABLE\x1b[0m"),
+        SolveResult::Unknown => println!("s \x1b[33mUNKNOWN\x1b[0m"),
     }
 }
 fn print_proof(proof: bool, assigns: &[BoolValue], formula: &[Clause], verbose: bool) {
@@ -95,6 +314,17 @@ fn print_proof(proof: bool, assigns: &[BoolValue], formula: &[Clause], verbose:
     }
 }
 
+/// Check the proof of unsatisfiability of the formula and print whether it is verified
+fn print_proof_check(cnf: &Cnf, proof_file: &str) {
+    match checker::check_proof(cnf, proof_file) {
+        Ok(()) => println!("s \x1b[32mVERIFIED\x1b[0m"),
+        Err(e) => {
+            println!("s \x1b[31mNOT VERIFIED\x1b[0m");
+            println!("c {e}");
+        }
+    }
+}
+
 fn sat_model_check(clauses: &[Clause], assigns: &[BoolValue]) -> bool {
     for clause in clauses.iter() {
         let mut satisfied = false;
@@ -122,28 +352,153 @@ fn sat_model_check(clauses: &[Clause], assigns: &[BoolValue]) -> bool {
     true
 }
 
-fn apply_solver<'a>(solver: &mut impl Solver<'a>, cnf: &Cnf, verbose: bool, proof: bool) {
+/// Apply the solver, returns its answer with the model if the formula is satisfiable
+fn apply_solver<'a>(
+    solver: &mut impl Solver<'a>,
+    cnf: &Cnf,
+    limits: Limits,
+    terminator: &Terminator,
+    verbose: bool,
+    proof: bool,
+) -> (SolveResult, Vec<BoolValue>) {
+    let start = std::time::Instant::now();
+    solver.set_limits(limits);
+    solver.set_terminator(terminator.clone());
+    let result = solver.solve();
+    if result == SolveResult::Unknown || verbose {
+        print_stats(solver.budget());
+    }
+    print_status(result);
+    if result != SolveResult::Unknown {
+        print_proof(proof, solver.assigns(), &cnf.clauses, verbose);
+    }
+    if verbose {
+        println!("Solved in {} seconds", start.elapsed().as_secs_f64())
+    }
+    match result {
+        SolveResult::Sat => (result, solver.assigns().clone()),
+        _ => (result, vec![]),
+    }
+}
+
+/// Apply the CDCL solver under the assumptions, the failed ones are printed if it is unsatisfiable.
+/// If a file is given, a proof of unsatisfiability is written to it in the given format.
+/// Returns the answer with the model of the original formula if it is satisfiable.
+#[allow(clippy::too_many_arguments)]
+fn apply_cdcl(
+    cnf: &Cnf,
+    config: &config::CdclConfig,
+    assumptions: &[Lit],
+    proof_file: Option<(&str, proof::ProofFormat)>,
+    preprocess: bool,
+    limits: Limits,
+    terminator: &Terminator,
+    verbose: bool,
+    proof: bool,
+) -> (SolveResult, Vec<BoolValue>) {
+    // The limits and the reported time include the preprocessing
     let start = std::time::Instant::now();
-    let is_sat = solver.solve();
-    print_status(is_sat);
-    print_proof(proof, solver.assigns(), &cnf.clauses, verbose);
+    // The eliminated vars cannot be assumed and the proofs cannot refer to the removed clauses
+    let preprocessor = (preprocess && assumptions.is_empty() && proof_file.is_none()).then(|| {
+        let mut preprocessor = preprocess::Preprocessor::new(cnf);
+        preprocessor.set_limits(limits);
+        preprocessor.set_terminator(terminator.clone());
+        preprocessor.simplify();
+        if verbose {
+            println!(
+                "Eliminated {} variables, {} clauses left",
+                preprocessor.eliminated(),
+                preprocessor.cnf().cl_num
+            );
+        }
+        preprocessor
+    });
+    let simplified = preprocessor.as_ref().map(|preprocessor| preprocessor.cnf());
+    let proof_writer = proof_file.map(|(path, format)| {
+        proof::Proof::create(path, format).unwrap_or_else(|e| {
+            eprintln!("Impossible to create the proof file {path}: {e}");
+            std::process::exit(6);
+        })
+    });
+    let mut solver = cdcl::CdclSolver::with_proof(
+        simplified.as_ref().unwrap_or(cnf),
+        config.clone(),
+        proof_writer,
+    );
+    solver.start_at(start);
+    solver.set_limits(limits);
+    solver.set_terminator(terminator.clone());
+    let result = solver.solve_with_assumptions(assumptions);
+    if let (Some(e), Some((path, _))) = (solver.proof_error(), proof_file) {
+        eprintln!("Impossible to write the proof file {path}: {e}");
+        std::process::exit(6);
+    }
+    if result == SolveResult::Unknown || verbose {
+        print_stats(solver.budget());
+    }
+    if verbose {
+        let stats = solver.stats();
+        println!("c reduced learnt clauses: {}", stats.reduced);
+        println!("c garbage collections: {}", stats.collections);
+        println!("c subsumed learnt clauses: {}", stats.subsumed);
+        println!("c strengthened learnt clauses: {}", stats.strengthened);
+        println!("c vivified clauses: {}", stats.vivified);
+    }
+    print_status(result);
+    if result == SolveResult::Unsat && !assumptions.is_empty() {
+        print!("c failed assumptions:");
+        for &lit in solver.failed_assumptions() {
+            print!(" {}", i32::from(lit));
+        }
+        println!();
+    }
+    let mut model = vec![];
+    if result != SolveResult::Unknown {
+        model = solver.assigns().clone();
+        if let Some(preprocessor) = &preprocessor {
+            preprocessor.extend_model(&mut model);
+        }
+        print_proof(proof, &model, &cnf.clauses, verbose);
+    }
     if verbose {
         println!("Solved in {} seconds", start.elapsed().as_secs_f64())
     }
+    if result != SolveResult::Sat {
+        model.clear();
+    }
+    (result, model)
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
-    let (flags, files) = get_args(args);
+    let (flags, options, files) = get_args(args);
+    let config = cdcl_config(&options);
+    let assumptions = assumptions(&options);
+    let limits = limits(&options);
+    let proof_file = |name: &str| {
+        options
+            .iter()
+            .find(|(option, _)| option == name)
+            .map(|(_, file)| file.as_str())
+    };
 
     let mut verbose = false;
     let mut proof = false;
+    let mut binary_drat = false;
+    let mut preprocess = true;
+    let mut mode = parser::ParseMode::Lenient;
     for flag in flags.iter() {
         if flag == "-v" || flag == "--verbose" {
             verbose = true;
         } else if flag == "--proof" {
             proof = true;
+        } else if flag == "--binary-drat" {
+            binary_drat = true;
+        } else if flag == "--no-preprocess" {
+            preprocess = false;
+        } else if flag == "--strict" {
+            mode = parser::ParseMode::Strict;
         } else if flag == "-h" || flag == "--help" {
             help();
             std::process::exit(0);
@@ -155,7 +510,40 @@ fn main() {
         std::process::exit(5)
     }
 
-    let mut cnfs = get_cnfs(files, verbose);
+    let drat = match (proof_file("--lrat"), proof_file("--drat")) {
+        (Some(file), _) => Some((file, proof::ProofFormat::Lrat)),
+        (None, Some(file)) if binary_drat => Some((file, proof::ProofFormat::BinaryDrat)),
+        (None, Some(file)) => Some((file, proof::ProofFormat::Drat)),
+        (None, None) => None,
+    };
+
+    let mut cnfs = get_cnfs(files, mode, verbose);
+    if let Some((_, proof_file)) = options.iter().find(|(option, _)| option == "--check-proof") {
+        for cnf in cnfs.iter() {
+            print_proof_check(cnf, proof_file);
+        }
+        return;
+    }
+    // The first Ctrl-C stops the current solve, the second one the process
+    let terminator = Terminator::default();
+    let handler = terminator.clone();
+    if let Err(e) = ctrlc::set_handler(move || {
+        if handler.is_terminated() {
+            std::process::exit(130);
+        }
+        handler.terminate();
+    }) {
+        eprintln!("Impossible to catch Ctrl-C: {e}");
+    }
+    let mut output = proof_file("--output").map(|path| {
+        let file = std::fs::File::create(path).unwrap_or_else(|e| {
+            eprintln!("Impossible to create the output file {path}: {e}");
+            std::process::exit(6);
+        });
+        std::io::BufWriter::new(file)
+    });
+    // The exit code of the answer for the last formula
+    let mut code = 0;
     let mut solver_type = vec![];
     for flag in flags {
         if flag == "--cdcl" {
@@ -167,47 +555,108 @@ fn main() {
             solver_type.push("khorn");
         } else if flag == "--dummy" {
             solver_type.push("dummy");
+        } else if flag == "--dpll" {
+            solver_type.push("dpll");
         } else if flag == "--2sat" {
             solver_type.push("2sat");
         }
     }
+    if let Some(name) = solver_type.iter().find(|&&name| name != "cdcl") {
+        if drat.is_some() || !assumptions.is_empty() {
+            eprintln!("Only the CDCL solver handles proofs and assumptions, not the {name} solver");
+            std::process::exit(6);
+        }
+    }
 
     for cnf in cnfs.iter_mut() {
-        if solver_type.is_empty() {
-            if sat2::is_2sat(cnf) {
+        let answers = if solver_type.is_empty() {
+            // Only the CDCL solver handles assumptions and proofs
+            vec![if !assumptions.is_empty() || drat.is_some() {
+                apply_cdcl(
+                    cnf,
+                    &config,
+                    &assumptions,
+                    drat,
+                    preprocess,
+                    limits,
+                    &terminator,
+                    verbose,
+                    proof,
+                )
+            } else if sat2::is_2sat(cnf) {
                 let mut solver = sat2::SAT2::new(cnf);
-                apply_solver(&mut solver, cnf, verbose, proof)
+                apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
             } else if khorn::is_khorn(cnf) {
                 let mut solver = khorn::KhornSolver::new(cnf);
-                apply_solver(&mut solver, cnf, verbose, proof)
+                apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
             } else {
-                let mut solver = cdcl::CdclSolver::new(cnf);
-                apply_solver(&mut solver, cnf, verbose, proof)
-            }
+                apply_cdcl(
+                    cnf,
+                    &config,
+                    &assumptions,
+                    drat,
+                    preprocess,
+                    limits,
+                    &terminator,
+                    verbose,
+                    proof,
+                )
+            }]
         } else {
-            for &solver_name in solver_type.iter() {
-                match solver_name {
-                    "cdcl" => {
-                        let mut solver = cdcl::CdclSolver::new(cnf);
-                        apply_solver(&mut solver, cnf, verbose, proof)
-                    }
+            solver_type
+                .iter()
+                .map(|&solver_name| match solver_name {
+                    "cdcl" => apply_cdcl(
+                        cnf,
+                        &config,
+                        &assumptions,
+                        drat,
+                        preprocess,
+                        limits,
+                        &terminator,
+                        verbose,
+                        proof,
+                    ),
                     "2sat" => {
                         let mut solver = sat2::SAT2::new(cnf);
-                        apply_solver(&mut solver, cnf, verbose, proof)
+                        apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
                     }
                     "khorn" => {
                         let mut solver = khorn::KhornSolver::new(cnf);
-                        apply_solver(&mut solver, cnf, verbose, proof)
+                        apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
                     }
                     "dummy" => {
                         let mut solver = tautosolver::TautoSolver::new(cnf);
-                        apply_solver(&mut solver, cnf, verbose, proof)
+                        apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
+                    }
+                    "dpll" => {
+                        let mut solver = dpll::Dpll::new(cnf);
+                        apply_solver(&mut solver, cnf, limits, &terminator, verbose, proof)
                     }
                     _ => panic!("Weird name"),
-                }
+                })
+                .collect()
+        };
+        for (result, model) in answers {
+            if let Some(output) = output.as_mut() {
+                write_answer(output, result, &model, cnf.var_num).unwrap_or_else(|e| {
+                    eprintln!("Impossible to write to the output file: {e}");
+                    std::process::exit(6);
+                });
             }
+            code = exit_code(result);
+        }
+        if terminator.is_terminated() {
+            break;
         }
     }
+    if let Some(output) = output.as_mut() {
+        output.flush().unwrap_or_else(|e| {
+            eprintln!("Impossible to write to the output file: {e}");
+            std::process::exit(6);
+        });
+    }
+    std::process::exit(code);
 }
 
 #[cfg(test)]
@@ -222,7 +671,62 @@ mod tests {
 
     use walkdir::WalkDir;
 
-    fn test_all_files(which: &str) {
+    /// The default configuration of the CDCL solver, and the ones changing one of its options
+    fn configs() -> Vec<config::CdclConfig> {
+        let default = config::CdclConfig::default();
+        let mut configs = vec![default.clone()];
+        for branching in [config::Branching::Vsids, config::Branching::Vmtf] {
+            configs.push(config::CdclConfig {
+                branching,
+                ..default.clone()
+            });
+        }
+        for restart in [
+            config::Restart::Never,
+            config::Restart::Geometric {
+                first: 100,
+                factor: 1.5,
+            },
+            config::Restart::Glucose {
+                window: 50,
+                margin: 0.8,
+            },
+        ] {
+            configs.push(config::CdclConfig {
+                restart,
+                ..default.clone()
+            });
+        }
+        for polarity in [
+            config::Polarity::False,
+            config::Polarity::True,
+            config::Polarity::Random,
+            config::Polarity::Target,
+        ] {
+            configs.push(config::CdclConfig {
+                polarity,
+                ..default.clone()
+            });
+        }
+        for minimization in [config::Minimization::None, config::Minimization::Local] {
+            configs.push(config::CdclConfig {
+                minimization,
+                ..default.clone()
+            });
+        }
+        configs
+    }
+
+    /// The files of tests/sat and tests/unsat also solved by `test_all_files`, which need enough
+    /// conflicts for the solver to restart
+    const MEDIUM_FILES: [&str; 4] = [
+        "tests/sat/AI/ais10.cnf",
+        "tests/sat/QG/qg3-08.cnf",
+        "tests/unsat/BF/bf0432-007.cnf",
+        "tests/unsat/PHOLE/hole6.cnf",
+    ];
+
+    fn test_all_files(which: &str, config: &config::CdclConfig) {
         let expected = match which {
             "sat" => true,
             "unsat" => false,
@@ -232,32 +736,38 @@ mod tests {
             }
         };
         let entries = WalkDir::new(format!("tests/small/{which}/"));
-        for entry in entries
+        let medium = MEDIUM_FILES
+            .iter()
+            .filter(|path| path.starts_with(&format!("tests/{which}/")))
+            .map(std::path::PathBuf::from);
+        for path in entries
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| !e.file_type().is_dir())
+            .map(|e| e.into_path())
+            .chain(medium)
         {
-            let path_str = entry.path().to_str().unwrap();
+            let path_str = path.to_str().unwrap();
 
             if path_str.ends_with(".cnf") {
-                let cnf = parse_cnf(path_str, false).unwrap();
+                let cnf = parse_cnf(path_str, ParseMode::Strict, false).unwrap();
                 let tmp_clauses = cnf.clauses.clone();
-                let mut solver = CdclSolver::new(&cnf);
-                let status = solver.solve();
+                let mut solver = CdclSolver::with_config(&cnf, config.clone());
+                let status = solver.solve() == SolveResult::Sat;
 
                 if status == expected {
                     if status && !sat_model_check(tmp_clauses.as_slice(), solver.assigns()) {
                         self::panic!(
-                            "Failed in my code T_T cnf: {}, Result: {}{:?}\x1b[0m Expected: {}{:?}\x1b[0m",
-                            path_str, if status {"\x1b[32m"} else {"\x1b[31m"}, status, if expected {"\x1b[32m"} else {"\x1b[31m"}, expected
+                            "Failed in my code T_T cnf: {}, config: {:?}, Result: {}{:?}\x1b[0m Expected: {}{:?}\x1b[0m",
+                            path_str, config, if status {"\x1b[32m"} else {"\x1b[31m"}, status, if expected {"\x1b[32m"} else {"\x1b[31m"}, expected
                         );
                     } else {
                         eprintln!("\x1b[32mSuccess\x1b[0m")
                     }
                 } else {
                     self::panic!(
-                        "Mismatch cnf: {}, Result: \x1b[31m{:?}\x1b[0m Expected: \x1b[32m{:?}\x1b[0m",
-                        path_str, status, expected
+                        "Mismatch cnf: {}, config: {:?}, Result: \x1b[31m{:?}\x1b[0m Expected: \x1b[32m{:?}\x1b[0m",
+                        path_str, config, status, expected
                     );
                 }
             }
@@ -266,15 +776,524 @@ mod tests {
 
     #[test]
     fn test_sat() {
-        test_all_files("sat");
+        for config in configs() {
+            test_all_files("sat", &config);
+        }
     }
     #[test]
     fn test_unsat() {
-        test_all_files("unsat");
+        for config in configs() {
+            test_all_files("unsat", &config);
+        }
+    }
+    #[test]
+    fn test_luby() {
+        // The first 15 elements
+        let sequence: Vec<u64> = (0..15).map(restart::luby).collect();
+        assert_eq!(sequence, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+    #[test]
+    fn test_rephasing() {
+        // Enough conflicts for the target phases to be reset twice, after 1000 and 3000 conflicts
+        let cnf = parse_cnf("tests/unsat/PHOLE/hole7.cnf", ParseMode::Strict, false).unwrap();
+        let config = config::CdclConfig {
+            polarity: config::Polarity::Target,
+            ..config::CdclConfig::default()
+        };
+        let mut solver = CdclSolver::with_config(&cnf, config);
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+        assert!(solver.budget().conflicts >= 3000);
     }
     #[test]
-    #[should_panic]
+    fn test_incremental() {
+        let lit = |i: i32| Lit::new(Var::from_id(i.unsigned_abs() as usize - 1), i < 0);
+        let cnf = Cnf {
+            var_num: 2,
+            cl_num: 1,
+            clauses: vec![vec![lit(1), lit(2)]],
+        };
+        let mut solver = CdclSolver::new(&cnf);
+        assert_eq!(
+            solver.solve_with_assumptions(&[lit(-1), lit(-2)]),
+            SolveResult::Unsat
+        );
+        assert_eq!(solver.solve_with_assumptions(&[lit(-1)]), SolveResult::Sat);
+        assert_eq!(solver.assigns()[1], BoolValue::True);
+        // Adding a clause with a new var
+        assert!(solver.add_clause(vec![lit(-2), lit(3)]));
+        assert_eq!(
+            solver.solve_with_assumptions(&[lit(-1), lit(-3)]),
+            SolveResult::Unsat
+        );
+        assert_eq!(solver.solve_with_assumptions(&[lit(-3)]), SolveResult::Sat);
+        assert_eq!(solver.assigns()[0], BoolValue::True);
+        assert!(solver.add_clause(vec![lit(-1)]));
+        assert!(solver.add_clause(vec![lit(-3)]));
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+        assert_eq!(solver.solve_with_assumptions(&[lit(1)]), SolveResult::Unsat);
+        assert!(solver.failed_assumptions().is_empty());
+    }
+
+    #[test]
+    fn test_failed_assumptions() {
+        let cnf = Cnf {
+            var_num: 4,
+            cl_num: 2,
+            clauses: vec![
+                vec![Lit::from(-1), Lit::from(-2), Lit::from(3)],
+                vec![Lit::from(-3), Lit::from(4)],
+            ],
+        };
+        let mut solver = CdclSolver::new(&cnf);
+        let assumptions = [Lit::from(1), Lit::from(-4), Lit::from(-5), Lit::from(2)];
+        assert_eq!(
+            solver.solve_with_assumptions(&assumptions),
+            SolveResult::Unsat
+        );
+        let mut failed = solver.failed_assumptions().to_vec();
+        failed.sort();
+        assert_eq!(failed, vec![Lit::from(1), Lit::from(2), Lit::from(-4)]);
+    }
+
+    #[test]
+    fn test_preprocess() {
+        for (dir, expected) in [("tests/small/sat", true), ("tests/small/unsat", false)] {
+            for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+                let path = entry.path().to_str().unwrap();
+                if !path.ends_with(".cnf") {
+                    continue;
+                }
+                let cnf = parse_cnf(path, ParseMode::Strict, false).unwrap();
+                let mut preprocessor = preprocess::Preprocessor::new(&cnf);
+                preprocessor.simplify();
+                let mut solver = CdclSolver::new(&preprocessor.cnf());
+                assert_eq!(solver.solve(), SolveResult::from(expected), "{path}");
+                if expected {
+                    let mut model = solver.assigns().clone();
+                    preprocessor.extend_model(&mut model);
+                    assert!(sat_model_check(&cnf.clauses, &model), "{path}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_proofs() {
+        use proof::{Proof, ProofFormat};
+        let cnf = parse_cnf("tests/small/unsat/tseitin5.cnf", ParseMode::Strict, false).unwrap();
+        let dir = std::env::temp_dir();
+        let lrat = dir.join("sat_solver_test.lrat");
+        let drat = dir.join("sat_solver_test.drat");
+        for (format, path) in [
+            (ProofFormat::Drat, &drat),
+            (ProofFormat::BinaryDrat, &drat),
+            (ProofFormat::Lrat, &lrat),
+        ] {
+            let path = path.to_str().unwrap();
+            let proof = Proof::create(path, format).unwrap();
+            let mut solver =
+                CdclSolver::with_proof(&cnf, config::CdclConfig::default(), Some(proof));
+            assert_eq!(solver.solve(), SolveResult::Unsat);
+            assert_eq!(checker::check_proof(&cnf, path), Ok(()));
+        }
+        // The formula is not refuted by unit propagation alone
+        let path = drat.to_str().unwrap();
+        std::fs::write(path, "").unwrap();
+        assert!(checker::check_proof(&cnf, path).is_err());
+
+        // The malformed proofs are rejected, without panicking
+        for (path, proof) in [
+            // Varints longer than 32 bits
+            (&drat, &b"a\xff\xff\xff\xff\xff\xff\x01\0"[..]),
+            (&drat, b"a\xff\xff\xff\xff\x7f\0"),
+            // A literal out of the i32 range, and negative ids
+            (&lrat, b"41 4294967296 0 1 0\n"),
+            (&lrat, b"-41 1 0 1 0\n"),
+            (&lrat, b"40 d -3 0\n"),
+        ] {
+            std::fs::write(path, proof).unwrap();
+            assert!(checker::check_proof(&cnf, path.to_str().unwrap()).is_err());
+        }
+    }
+
+    /// Solve unsatisfiable formulas with the configuration, check the LRAT proofs of the solver
+    /// and return its statistics on each formula
+    fn check_lrat_proofs(config: &config::CdclConfig, name: &str) -> Vec<cdcl::Stats> {
+        use proof::{Proof, ProofFormat};
+        let path = std::env::temp_dir().join(format!("sat_solver_{name}.lrat"));
+        let path = path.to_str().unwrap();
+        [
+            "tests/small/unsat/tseitin5.cnf",
+            "tests/small/unsat/random2.cnf",
+            "tests/unsat/PHOLE/hole6.cnf",
+        ]
+        .iter()
+        .map(|file| {
+            let cnf = parse_cnf(file, ParseMode::Strict, false).unwrap();
+            let proof = Proof::create(path, ProofFormat::Lrat).unwrap();
+            let mut solver = CdclSolver::with_proof(&cnf, config.clone(), Some(proof));
+            assert_eq!(solver.solve(), SolveResult::Unsat, "{file}");
+            assert_eq!(checker::check_proof(&cnf, path), Ok(()), "{file}");
+            *solver.stats()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_reduce_db() {
+        let config = config::CdclConfig {
+            reduce_interval: 1,
+            ..config::CdclConfig::default()
+        };
+        let stats = check_lrat_proofs(&config, "reduce_db");
+        assert!(stats.iter().any(|stats| stats.reduced > 0));
+    }
+
+    #[test]
+    fn test_garbage_collect() {
+        // The clauses moved by the compactions keep their ids in the proofs
+        let config = config::CdclConfig {
+            reduce_interval: 1,
+            reduce_increment: 0,
+            ..config::CdclConfig::default()
+        };
+        let stats = check_lrat_proofs(&config, "garbage_collect");
+        assert!(stats.iter().any(|stats| stats.collections > 0));
+    }
+
+    #[test]
+    fn test_subsumption() {
+        use subsumption::{Simplification, Subsumer};
+        let clause = |lits: &[i32]| lits.iter().map(|&x| Lit::from(x)).collect::<Clause>();
+        let mut subsumer = Subsumer::new(5);
+        subsumer.add(clause(&[1, 2]), true);
+        subsumer.add(clause(&[1, 2, 3]), true);
+        subsumer.add(clause(&[-1, 2, 4]), true);
+        // Only the candidates are simplified
+        subsumer.add(clause(&[1, 2, 5]), false);
+        assert_eq!(
+            subsumer.run(),
+            vec![
+                Simplification::Subsumed { clause: 1, by: 0 },
+                Simplification::Strengthened {
+                    clause: 2,
+                    by: 0,
+                    lit: Lit::from(-1)
+                },
+            ]
+        );
+        assert!(subsumer.is_removed(1) && !subsumer.is_removed(3));
+        assert_eq!(subsumer.clause(2), clause(&[2, 4]));
+
+        // The learnt clauses are simplified after each conflict
+        let config = config::CdclConfig {
+            subsume_interval: 1,
+            ..config::CdclConfig::default()
+        };
+        let stats = check_lrat_proofs(&config, "subsumption");
+        assert!(stats.iter().any(|stats| stats.subsumed > 0));
+        assert!(stats.iter().any(|stats| stats.strengthened > 0));
+    }
+
+    #[test]
+    fn test_probing_and_equivalences() {
+        // 3 implies 4 and its negation, and 1 and 2 are equivalent
+        let input = "p cnf 5 6\n-1 2 0\n1 -2 0\n-3 4 0\n-3 -4 0\n2 3 5 0\n-1 -5 4 0\n";
+        let cnf = parser::parse_dimacs(input.as_bytes(), ParseMode::Strict).unwrap();
+        let mut preprocessor = preprocess::Preprocessor::new(&cnf);
+        preprocessor.probe();
+        assert!(preprocessor.cnf().clauses.contains(&vec![Lit::from(-3)]));
+        preprocessor.substitute_equivalences();
+        let simplified = preprocessor.cnf();
+        assert_eq!(preprocessor.eliminated(), 1);
+        assert!(simplified
+            .clauses
+            .iter()
+            .flatten()
+            .all(|lit| lit.get_var() != Var(1)));
+
+        let mut solver = CdclSolver::new(&simplified);
+        assert_eq!(solver.solve(), SolveResult::Sat);
+        let mut model = solver.assigns().clone();
+        preprocessor.extend_model(&mut model);
+        assert_eq!(model[0], model[1]);
+        assert!(sat_model_check(&cnf.clauses, &model));
+    }
+
+    #[test]
+    fn test_vivify() {
+        // Every simplification of the clauses runs after each conflict or restart
+        let config = config::CdclConfig {
+            restart: config::Restart::Luby { unit: 1 },
+            reduce_interval: 1,
+            subsume_interval: 1,
+            vivify_interval: 1,
+            vivify_originals: true,
+            ..config::CdclConfig::default()
+        };
+        let stats = check_lrat_proofs(&config, "vivify");
+        assert!(stats.iter().any(|stats| stats.vivified > 0));
+    }
+
+    #[test]
+    fn test_blocked_clauses() {
+        // 1 2 is blocked on 1: its resolvent with -1 -2 is a tautology
+        let input = "p cnf 3 3\n1 2 0\n-1 -2 0\n-2 3 0\n";
+        let cnf = parser::parse_dimacs(input.as_bytes(), ParseMode::Strict).unwrap();
+        let mut preprocessor = preprocess::Preprocessor::new(&cnf);
+        preprocessor.eliminate_blocked();
+        let simplified = preprocessor.cnf();
+        assert!(simplified.clauses.len() < cnf.clauses.len());
+        // All false satisfies what is left but not 1 2, the reconstruction makes 1 or 2 true
+        let mut model = vec![BoolValue::False; cnf.var_num];
+        assert!(sat_model_check(&simplified.clauses, &model));
+        preprocessor.extend_model(&mut model);
+        assert!(sat_model_check(&cnf.clauses, &model));
+    }
+
+    #[test]
+    fn test_clause_ids() {
+        // The ids of the proofs do not fit in 32 bits after enough clauses
+        let mut arena = clause_arena::ClauseArena::default();
+        let id = (1 << 40) + 5;
+        let clause = vec![Lit::from(1), Lit::from(-2)];
+        let cref = arena.alloc(&clause, true, 3, id);
+        assert_eq!(arena.id(cref), id);
+        let mut to = clause_arena::ClauseArena::default();
+        let moved = arena.relocate(cref, &mut to);
+        assert_eq!((to.id(moved), &to[moved]), (id, &clause[..]));
+    }
+
+    #[test]
+    fn test_limits() {
+        let cnf = parse_cnf("tests/small/unsat/tseitin5.cnf", ParseMode::Strict, false).unwrap();
+        let mut solver = CdclSolver::new(&cnf);
+        solver.set_limits(Limits {
+            conflicts: Some(1),
+            ..Limits::default()
+        });
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        // The solver goes on where it stopped once the limits are lifted
+        solver.set_limits(Limits::default());
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+
+        let mut solver = tautosolver::TautoSolver::new(&cnf);
+        solver.set_limits(Limits {
+            decisions: Some(10),
+            ..Limits::default()
+        });
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+
+        // The preprocessing stops at the time limit, the formula is then left as it is
+        let cnf = parse_cnf("tests/small/sat/ais6.cnf", ParseMode::Strict, false).unwrap();
+        let mut preprocessor = preprocess::Preprocessor::new(&cnf);
+        preprocessor.set_limits(Limits {
+            time: Some(std::time::Duration::ZERO),
+            ..Limits::default()
+        });
+        preprocessor.simplify();
+        assert_eq!(preprocessor.eliminated(), 0);
+        assert_eq!(preprocessor.cnf().cl_num, cnf.cl_num);
+
+        // The time of the solver can include what was done before the call
+        let start = std::time::Instant::now();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let mut solver = CdclSolver::new(&cnf);
+        solver.start_at(start);
+        solver.set_limits(Limits {
+            time: Some(std::time::Duration::from_millis(5)),
+            ..Limits::default()
+        });
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        assert!(solver.budget().elapsed() >= std::time::Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_terminator() {
+        // Stopped from another thread in the middle of a long search
+        let cnf = parse_cnf("tests/unsat/PHOLE/hole10.cnf", ParseMode::Strict, false).unwrap();
+        let mut solver = CdclSolver::new(&cnf);
+        let terminator = Terminator::default();
+        solver.set_terminator(terminator.clone());
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            terminator.terminate();
+        });
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        handle.join().unwrap();
+
+        // A terminated solver answers Unknown until it is given another terminator
+        let cnf = parse_cnf("tests/small/unsat/tseitin5.cnf", ParseMode::Strict, false).unwrap();
+        let mut solver = CdclSolver::new(&cnf);
+        let terminator = Terminator::default();
+        terminator.terminate();
+        solver.set_terminator(terminator);
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        solver.set_terminator(Terminator::default());
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+        let mut solver = dpll::Dpll::new(&cnf);
+        let terminator = Terminator::default();
+        terminator.terminate();
+        solver.set_terminator(terminator);
+        assert_eq!(solver.solve(), SolveResult::Unknown);
+        solver.set_terminator(Terminator::default());
+        assert_eq!(solver.solve(), SolveResult::Unsat);
+
+        // The preprocessing stops the same way
+        let cnf = parse_cnf("tests/small/sat/ais6.cnf", ParseMode::Strict, false).unwrap();
+        let mut preprocessor = preprocess::Preprocessor::new(&cnf);
+        let terminator = Terminator::default();
+        terminator.terminate();
+        preprocessor.set_terminator(terminator);
+        preprocessor.simplify();
+        assert_eq!(preprocessor.eliminated(), 0);
+        assert_eq!(preprocessor.cnf().cl_num, cnf.cl_num);
+    }
+
+    #[test]
+    fn test_output() {
+        let cnf = parse_cnf("tests/small/sat/php3-4.cnf", ParseMode::Strict, false).unwrap();
+        let mut solver = CdclSolver::new(&cnf);
+        let result = solver.solve();
+        let mut output = vec![];
+        write_answer(&mut output, result, solver.assigns(), cnf.var_num).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("s SATISFIABLE"));
+        // The model read back from the "v" lines satisfies the formula
+        let lits: Vec<i32> = lines
+            .flat_map(|line| line.strip_prefix("v ").unwrap().split(' '))
+            .map(|lit| lit.parse().unwrap())
+            .collect();
+        assert_eq!(lits.len(), cnf.var_num + 1);
+        assert_eq!(lits.last(), Some(&0));
+        let model: Vec<BoolValue> = lits[..cnf.var_num]
+            .iter()
+            .map(|&lit| BoolValue::from((lit < 0) as i8))
+            .collect();
+        assert!(sat_model_check(&cnf.clauses, &model));
+        assert_eq!(exit_code(result), 10);
+
+        let mut output = vec![];
+        write_answer(&mut output, SolveResult::Unknown, &[], 0).unwrap();
+        assert_eq!(output, b"s UNKNOWN\n");
+
+        // Every declared var is in the model, even without clause
+        let cnf = Cnf {
+            var_num: 3,
+            cl_num: 0,
+            clauses: vec![],
+        };
+        let mut solver = sat2::SAT2::new(&cnf);
+        let result = solver.solve();
+        let mut output = vec![];
+        write_answer(&mut output, result, solver.assigns(), cnf.var_num).unwrap();
+        assert_eq!(output, b"s SATISFIABLE\nv 1 2 3\nv 0\n");
+    }
+
+    #[test]
     fn test_parsing() {
-        parse_cnf("./tests/parsing/no_p.cnf", false).unwrap();
+        let error = |path| parse_cnf(path, ParseMode::Strict, false).unwrap_err();
+        assert!(matches!(
+            error("./tests/parsing/no_p.cnf"),
+            parser::ParseError::MissingHeader { line: 1 }
+        ));
+        assert!(matches!(
+            error("./tests/parsing/bad_header.cnf"),
+            parser::ParseError::InvalidNumber {
+                line: 2,
+                column: 9,
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("./tests/parsing/too_many_vars.cnf"),
+            parser::ParseError::TooManyVariables {
+                line: 3,
+                column: 4,
+                expected: 2,
+                found: 3
+            }
+        ));
+        assert!(matches!(
+            error("./tests/parsing/missing.cnf"),
+            parser::ParseError::Io(_)
+        ));
+
+        // The compressed files are read as the original one
+        let cnf = parse_cnf("tests/small/unsat/tseitin5.cnf", ParseMode::Strict, false).unwrap();
+        for extension in ["gz", "xz", "bz2"] {
+            let path = format!("tests/parsing/tseitin5.cnf.{extension}");
+            let compressed = parse_cnf(&path, ParseMode::Strict, false).unwrap();
+            assert_eq!(compressed.clauses, cnf.clauses);
+            // Even when the input comes byte by byte
+            let file = std::fs::File::open(&path).unwrap();
+            let input = parser::decompress(std::io::BufReader::with_capacity(1, file)).unwrap();
+            let compressed = parser::parse_dimacs(input, ParseMode::Strict).unwrap();
+            assert_eq!(compressed.clauses, cnf.clauses);
+        }
+        // Only the magic bytes give the format, not the extension
+        let path = std::env::temp_dir().join("sat_solver_test_plain.cnf.gz");
+        std::fs::write(&path, "p cnf 1 1\n1 0\n").unwrap();
+        let plain = parse_cnf(path.to_str().unwrap(), ParseMode::Strict, false).unwrap();
+        assert_eq!(plain.clauses, vec![vec![Lit::from(1)]]);
+
+        // The clauses may span lines or share one, and the SATLIB trailer ends the formula
+        let input = "c comment\np cnf 3 3\n1 -2\n0 2 3 0 -1\n-3 0\n%\n0\n";
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            let cnf = parser::parse_dimacs(input.as_bytes(), mode).unwrap();
+            let clauses: Vec<Vec<i32>> = cnf
+                .clauses
+                .iter()
+                .map(|clause| clause.iter().map(|&lit| i32::from(lit)).collect())
+                .collect();
+            assert_eq!(clauses, vec![vec![1, -2], vec![2, 3], vec![-1, -3]]);
+        }
+
+        // Only the lenient mode accepts a wrong clause number in the header and a missing 0
+        let input = "p cnf 2 3\n1 2 0\n-1";
+        assert!(matches!(
+            parser::parse_dimacs(input.as_bytes(), ParseMode::Strict),
+            Err(parser::ParseError::UnterminatedClause { line: 3 })
+        ));
+        let cnf = parser::parse_dimacs(input.as_bytes(), ParseMode::Lenient).unwrap();
+        assert_eq!((cnf.var_num, cnf.cl_num), (2, 2));
+        // But both reject the vars above the header
+        for mode in [ParseMode::Strict, ParseMode::Lenient] {
+            assert!(matches!(
+                parser::parse_dimacs("p cnf 2 1\n1 2147483647 0\n".as_bytes(), mode),
+                Err(parser::ParseError::TooManyVariables {
+                    line: 2,
+                    column: 3,
+                    expected: 2,
+                    found: 2147483647
+                })
+            ));
+            // The vars stay within the range of the literals, with or without header
+            assert!(matches!(
+                parser::parse_dimacs("p cnf 99999999999 1\n1 0\n".as_bytes(), mode),
+                Err(parser::ParseError::InvalidHeader { line: 1 })
+            ));
+        }
+        assert!(matches!(
+            parser::parse_dimacs("-2147483648 0\n".as_bytes(), ParseMode::Lenient),
+            Err(parser::ParseError::TooManyVariables {
+                line: 1,
+                column: 1,
+                expected: parser::MAX_VAR_NUM,
+                found: 2147483648
+            })
+        ));
+        assert!(matches!(
+            parser::parse_dimacs("p cnf 2 2\n1 2 0\n-1".as_bytes(), ParseMode::Strict),
+            Err(parser::ParseError::UnterminatedClause { line: 3 })
+        ));
+        assert!(matches!(
+            parser::parse_dimacs("p cnf 2 2\n1 2 0\n".as_bytes(), ParseMode::Strict),
+            Err(parser::ParseError::TooFewClauses {
+                expected: 2,
+                found: 1
+            })
+        ));
     }
 }
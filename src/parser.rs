@@ -1,91 +1,306 @@
-use std::io::BufRead;
+use std::io::{BufRead, Read};
 
-/// Parse the cnf file given as input.
-/// The expected format is dimacs but with some changes.
-/// As for dimacs, we require a line containing "p cnf <var number> <clause number>" and each variable are represented by an integer.
-/// But we do not require that the clause ends with a 0, however each clause HAS to be represented in a single line.
-/// For example if one want to represent the formula (x1 \/ x2) /\ (¬ x2 \/ ¬x1) /\ x1 they can write:
-/// ```cnf
-/// p cnf 2 3
-/// 1 2 0
-/// -1 -2 0
-/// 1 0
-/// ```
-pub fn parse_cnf(path: &str, verbose: bool) -> std::io::Result<crate::all_types::Cnf> {
-    let input = match std::fs::File::open(path) {
-        Err(e) => panic!("Impossible to open file: {e}"),
-        Ok(f) => f,
+/// The largest var number, the literals are stored on 32 bits
+pub const MAX_VAR_NUM: usize = (u32::MAX >> 1) as usize - 1;
+
+/// How closely the input has to follow the DIMACS format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// The "p" line comes before the clauses and its numbers are exact, every clause ends with a 0
+    /// and the comment lines start with a "c" token.
+    Strict,
+    /// The clause number of the "p" line is only a hint and the line may be missing, the last
+    /// clause may miss its 0 and every line starting with a 'c' is a comment. The vars are still
+    /// bounded by the "p" line when there is one.
+    #[default]
+    Lenient,
+}
+
+/// The reasons why a cnf file is rejected, the lines and columns start from 1
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file cannot be opened or read
+    Io(std::io::Error),
+    /// No "p cnf <var number> <clause number>" line before the clauses, in strict mode
+    MissingHeader { line: usize },
+    /// The "p" line is not "p cnf <var number> <clause number>", or its var number is above
+    /// `MAX_VAR_NUM`
+    InvalidHeader { line: usize },
+    /// The token is not a non negative integer
+    InvalidNumber {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    /// The literal at this position has a var above the number of the "p" line, or above
+    /// `MAX_VAR_NUM` without it
+    TooManyVariables {
+        line: usize,
+        column: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The token is neither a literal nor the 0 ending a clause
+    InvalidLiteral {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    /// The clause ending at this line is beyond the number of the "p" line
+    TooManyClauses { line: usize, expected: usize },
+    /// There are less clauses than the number of the "p" line
+    TooFewClauses { expected: usize, found: usize },
+    /// The last clause, which ends at this line, has no 0
+    UnterminatedClause { line: usize },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{e}"),
+            ParseError::MissingHeader { line } => write!(
+                f,
+                "line {line}: a line containing \"p cnf <var number> <clause number>\" is expected before the clauses"
+            ),
+            ParseError::InvalidHeader { line } => write!(
+                f,
+                "line {line}: the \"p\" line should be \"p cnf <var number> <clause number>\" with at most {MAX_VAR_NUM} vars"
+            ),
+            ParseError::InvalidNumber {
+                line,
+                column,
+                token,
+            } => write!(
+                f,
+                "line {line}, column {column}: expected a non negative integer, got \"{token}\""
+            ),
+            ParseError::TooManyVariables {
+                line,
+                column,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}, column {column}: too many variables, expected {expected} but found {found}"
+            ),
+            ParseError::InvalidLiteral {
+                line,
+                column,
+                token,
+            } => write!(
+                f,
+                "line {line}, column {column}: expected a literal, got \"{token}\""
+            ),
+            ParseError::TooManyClauses { line, expected } => write!(
+                f,
+                "line {line}: too many clauses, expected {expected}"
+            ),
+            ParseError::TooFewClauses { expected, found } => write!(
+                f,
+                "too few clauses, expected {expected} but found {found}"
+            ),
+            ParseError::UnterminatedClause { line } => {
+                write!(f, "line {line}: the last clause does not end with a 0")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<std::io::Error> for ParseError {
+    fn from(e: std::io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+/// The tokens of the line separated by whitespaces, with their column
+fn tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (i, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+    {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(first), true) => {
+                tokens.push((first + 1, &line[first..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    tokens
+}
+
+/// Decompress the input on the fly when it starts with the magic bytes of gzip, xz or bzip2
+pub fn decompress(mut input: impl BufRead + 'static) -> std::io::Result<Box<dyn BufRead>> {
+    // A single read may give less bytes than the longest magic
+    let mut magic = vec![];
+    input.by_ref().take(6).read_to_end(&mut magic)?;
+    let gzip = magic.starts_with(&[0x1f, 0x8b]);
+    let xz = magic.starts_with(b"\xfd7zXZ\0");
+    let bzip2 = magic.starts_with(b"BZh");
+    let input = std::io::Cursor::new(magic).chain(input);
+    Ok(if gzip {
+        Box::new(std::io::BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(input),
+        ))
+    } else if xz {
+        Box::new(std::io::BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(input),
+        ))
+    } else if bzip2 {
+        Box::new(std::io::BufReader::new(
+            bzip2::bufread::MultiBzDecoder::new(input),
+        ))
+    } else {
+        Box::new(input)
+    })
+}
+
+/// Parse the cnf file given as input, or the standard input if the path is "-", see `parse_dimacs`
+/// for the format.
+/// The inputs compressed with gzip, xz or bzip2 are decompressed while they are read.
+pub fn parse_cnf(
+    path: &str,
+    mode: ParseMode,
+    verbose: bool,
+) -> Result<crate::all_types::Cnf, ParseError> {
+    let input = if path == "-" {
+        decompress(std::io::stdin().lock())?
+    } else {
+        decompress(std::io::BufReader::new(std::fs::File::open(path)?))?
     };
     if verbose {
         println!("Reading file: {path}")
     }
-    let reader = std::io::BufReader::new(input);
-    let mut var_num = 0;
-    let mut cl_num = 0;
+    parse_dimacs(input, mode)
+}
+
+/// Parse a formula in the dimacs format, reading the input line by line.
+/// After the comments and a line "p cnf <var number> <clause number>", the clauses are given as
+/// sequences of integers ended by a 0, they may span several lines or share one.
+/// For example the formula (x1 \/ x2) /\ (¬ x2 \/ ¬x1) /\ x1 can be written:
+/// ```cnf
+/// c a comment
+/// p cnf 2 3
+/// 1 2 0
+/// -1 -2
+/// 0 1 0
+/// ```
+/// A line starting with "%" ends the formula, as in the SATLIB files, and the first error found is returned.
+pub fn parse_dimacs(
+    mut reader: impl BufRead,
+    mode: ParseMode,
+) -> Result<crate::all_types::Cnf, ParseError> {
+    let strict = mode == ParseMode::Strict;
+    let mut header = None;
     let mut var_count = 0;
-    let mut clause_count = 0;
     let mut clauses = vec![];
-    let mut seen_p = false;
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-
-        if line.starts_with('c') {
-            // comments
-            continue;
+    let mut clause = vec![];
+    let mut line = String::new();
+    let mut line_num = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
         }
-        let values: Vec<&str> = line.split_whitespace().collect();
-        if values.is_empty() {
+        line_num += 1;
+        let values = tokens(&line);
+        match values.first() {
             // empty line
-            continue;
+            None => continue,
+            Some((_, "%")) => break,
+            Some((_, "c")) => continue,
+            Some((_, token)) if !strict && token.starts_with('c') => continue,
+            Some((_, "p")) => {
+                if strict && header.is_some() {
+                    return Err(ParseError::InvalidHeader { line: line_num });
+                }
+                let number = |(column, token): (usize, &str)| {
+                    token
+                        .parse::<usize>()
+                        .map_err(|_| ParseError::InvalidNumber {
+                            line: line_num,
+                            column,
+                            token: token.to_string(),
+                        })
+                };
+                match values[..] {
+                    [_, (_, "cnf"), var_num, cl_num] => {
+                        let var_num = number(var_num)?;
+                        if var_num > MAX_VAR_NUM {
+                            return Err(ParseError::InvalidHeader { line: line_num });
+                        }
+                        header = Some((var_num, number(cl_num)?))
+                    }
+                    _ => return Err(ParseError::InvalidHeader { line: line_num }),
+                }
+                continue;
+            }
+            _ => (),
         }
-        if values[0] == "p" {
-            seen_p = true;
-            if let Some(v) = values.get(2) {
-                // Get the number of variables
-                var_num = v.parse::<usize>().unwrap();
-            } else {
-                eprintln!("Error parsing, \"p\" line should contains the number of variables.");
-            };
-            if let Some(v) = values.get(3) {
-                // Get the number of variables
-                cl_num = v.parse::<usize>().unwrap();
-            } else {
-                eprintln!("Error parsing, \"p\" line should contains the number of clauses.");
-            };
-            continue;
+        if strict && header.is_none() {
+            return Err(ParseError::MissingHeader { line: line_num });
         }
 
-        let values: Vec<_> = values
-            .into_iter()
-            .filter_map(|x| x.parse::<i32>().ok())
-            .take_while(|x| *x != 0)
-            .collect();
-
-        clause_count += 1;
-        if values.is_empty() {
-            // empty clause
-            continue;
+        for (column, token) in values {
+            let Ok(x) = token.parse::<i32>() else {
+                return Err(ParseError::InvalidLiteral {
+                    line: line_num,
+                    column,
+                    token: token.to_string(),
+                });
+            };
+            if x == 0 {
+                if let Some((_, cl_num)) = header.filter(|_| strict) {
+                    if clauses.len() == cl_num {
+                        return Err(ParseError::TooManyClauses {
+                            line: line_num,
+                            expected: cl_num,
+                        });
+                    }
+                }
+                clauses.push(std::mem::take(&mut clause));
+                continue;
+            }
+            let var = x.unsigned_abs() as usize;
+            let var_num = header.map_or(MAX_VAR_NUM, |(var_num, _)| var_num);
+            if var > var_num {
+                return Err(ParseError::TooManyVariables {
+                    line: line_num,
+                    column,
+                    expected: var_num,
+                    found: var,
+                });
+            }
+            var_count = var_count.max(var);
+            clause.push(crate::all_types::Lit::from(x));
         }
-        let clause: Vec<crate::all_types::Lit> = values
-            .iter()
-            .map(|&x| {
-                var_count = var_count.max(x.unsigned_abs() as usize);
-                crate::all_types::Lit::from(x)
-            })
-            .collect();
-        clauses.push(clause);
-    }
-    if !seen_p {
-        panic!("A line containing \"p cnf <var number> <clause number>\" is expected.")
     }
-    if var_count > var_num {
-        panic!("Too much variables, expected {var_num} but found up to {var_count}")
-    }
-    if clause_count > cl_num {
-        panic!("Too much clauses, expected {cl_num} but found up to {clause_count}")
+    if !clause.is_empty() {
+        if strict {
+            return Err(ParseError::UnterminatedClause { line: line_num });
+        }
+        clauses.push(clause);
     }
-    if cl_num != clauses.len() {
+    let var_num = match header {
+        Some((var_num, cl_num)) if strict => {
+            if clauses.len() < cl_num {
+                return Err(ParseError::TooFewClauses {
+                    expected: cl_num,
+                    found: clauses.len(),
+                });
+            }
+            var_num
+        }
+        Some((var_num, _)) => var_num,
+        None => var_count,
+    };
+    let cl_num = clauses.len();
+    if clauses.iter().any(|clause| clause.is_empty()) {
         // We found an empty clause, ie the formula is false.
         Ok(crate::all_types::Cnf {
             var_num,
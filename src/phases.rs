@@ -0,0 +1,122 @@
+use crate::all_types::*;
+use crate::config::Polarity;
+use rand::Rng;
+
+/// The number of conflicts between the first two rephasings, the next ones are more and more spaced.
+const REPHASE_INTERVAL: u64 = 1000;
+
+/// The phases the saved phases are reset to when rephasing, in order.
+#[derive(Debug, Clone, Copy)]
+enum Rephase {
+    Best,
+    Original,
+    Inverted,
+    Random,
+}
+const REPHASE_CYCLE: [Rephase; 6] = [
+    Rephase::Best,
+    Rephase::Original,
+    Rephase::Best,
+    Rephase::Inverted,
+    Rephase::Best,
+    Rephase::Random,
+];
+
+/// Choose the value of the decision variables.
+/// The saved phases are kept by the working model, this keeps track of the target and best phases.
+#[derive(Debug)]
+pub struct Phases {
+    polarity: Polarity,
+    // The assignment of the longest conflict free trail since the last rephasing
+    target: Vec<BoolValue>,
+    target_len: usize,
+    // The assignment of the longest conflict free trail ever
+    best: Vec<BoolValue>,
+    best_len: usize,
+    rephased: u64,
+    next_rephase: u64,
+}
+
+impl Phases {
+    pub fn new(n: usize, polarity: Polarity) -> Self {
+        Phases {
+            polarity,
+            target: vec![BoolValue::Undefined; n],
+            target_len: 0,
+            best: vec![BoolValue::Undefined; n],
+            best_len: 0,
+            rephased: 0,
+            next_rephase: REPHASE_INTERVAL,
+        }
+    }
+
+    pub fn new_var(&mut self) {
+        self.target.push(BoolValue::Undefined);
+        self.best.push(BoolValue::Undefined);
+    }
+
+    /// The literal to assign for the decision variable
+    pub fn decide(&self, var: Var, model: &WorkingModel, rng: &mut impl Rng) -> Lit {
+        let value = match self.polarity {
+            Polarity::False => BoolValue::False,
+            Polarity::True => BoolValue::True,
+            Polarity::Saved => model.saved_phase(var),
+            Polarity::Random => BoolValue::from(rng.gen_bool(0.5) as i8),
+            Polarity::Target => match self.target[var] {
+                BoolValue::Undefined => model.saved_phase(var),
+                value => value,
+            },
+        };
+        Lit::new(var, value == BoolValue::False)
+    }
+
+    /// Called on each conflict before backtracking, remember the conflict free part of the trail
+    /// if it is the longest seen so far
+    pub fn on_conflict(&mut self, model: &WorkingModel) {
+        if self.polarity != Polarity::Target {
+            return;
+        }
+        let level = model.decision_level();
+        let trail = model.trail();
+        let len = trail.len() - model.trail_above(level.saturating_sub(1)).len();
+        if len > self.target_len {
+            self.target_len = len;
+            for &lit in &trail[..len] {
+                self.target[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
+            }
+        }
+        if len > self.best_len {
+            self.best_len = len;
+            for &lit in &trail[..len] {
+                self.best[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
+            }
+        }
+    }
+
+    pub fn should_rephase(&self, conflicts: u64) -> bool {
+        self.polarity == Polarity::Target && conflicts >= self.next_rephase
+    }
+
+    /// Reset the saved phases and forget the target phases, to leave the region of the search
+    /// space they lead to. It should be done at level 0 so that the saved phases are not overwritten.
+    pub fn rephase(&mut self, conflicts: u64, model: &mut WorkingModel, rng: &mut impl Rng) {
+        let kind = REPHASE_CYCLE[self.rephased as usize % REPHASE_CYCLE.len()];
+        self.rephased += 1;
+        self.next_rephase = conflicts + REPHASE_INTERVAL * (self.rephased + 1);
+        for (i, target) in self.target.iter_mut().enumerate() {
+            let var = Var::from_id(i);
+            let value = match kind {
+                Rephase::Best => match self.best[var] {
+                    BoolValue::Undefined => model.saved_phase(var),
+                    value => value,
+                },
+                Rephase::Original => BoolValue::False,
+                Rephase::Inverted => BoolValue::True,
+                Rephase::Random => BoolValue::from(rng.gen_bool(0.5) as i8),
+            };
+            model.set_saved_phase(var, value);
+            *target = BoolValue::Undefined;
+        }
+        self.target_len = 0;
+    }
+}
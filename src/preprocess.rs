@@ -0,0 +1,506 @@
+use crate::all_types::*;
+use crate::reconstruction::ReconstructionStack;
+use crate::solver::{Budget, Limits, Terminator};
+use crate::subsumption::Subsumer;
+use petgraph::graph::{DiGraph, NodeIndex};
+
+/// Vars occurring in more clauses than this on both sides are not eliminated,
+/// and the clauses are not checked for being blocked on a literal whose negation occurs more.
+const OCCURRENCE_LIMIT: usize = 16;
+/// Vars whose elimination produces a resolvent longer than this are not eliminated.
+const RESOLVENT_LIMIT: usize = 20;
+/// How many more clauses than it removes the elimination of a var may add.
+const CLAUSE_GROWTH: usize = 0;
+/// The number of literals visited by the propagations of the probing before it stops.
+const PROBING_LIMIT: usize = 10_000_000;
+
+/// Simplify a formula before solving it.
+/// The literals implied at level 0 are found by failed literal probing and the equivalent literals
+/// are replaced by a representative of their class.
+/// The subsumed clauses are removed and the clauses are strengthened by self-subsuming resolution.
+/// The variables are eliminated by resolution (SatELite's bounded variable elimination): the clauses
+/// containing a var are replaced by all their resolvents on it, when there are not more of them.
+/// The blocked clauses are removed.
+/// The clauses removed by the last two are kept on a reconstruction stack to extend the models
+/// of the simplified formula.
+/// Each technique stops early once the time limit is reached or the terminator is triggered, the
+/// formula is then only partially simplified.
+#[derive(Debug)]
+pub struct Preprocessor {
+    var_num: usize,
+    clauses: Vec<Clause>,
+    removed: Vec<bool>,
+    // For each literal, the clauses containing it
+    occurs: Vec<Vec<usize>>,
+    eliminated: Vec<bool>,
+    stack: ReconstructionStack,
+    unsat: bool,
+    // The time limit counts from the creation of the preprocessor
+    budget: Budget,
+}
+
+impl Preprocessor {
+    pub fn new(cnf: &Cnf) -> Self {
+        let mut preprocessor = Preprocessor {
+            var_num: cnf.var_num,
+            clauses: vec![],
+            removed: vec![],
+            occurs: vec![vec![]; 2 * cnf.var_num],
+            eliminated: vec![false; cnf.var_num],
+            stack: ReconstructionStack::default(),
+            unsat: false,
+            budget: Budget::default(),
+        };
+        // Tautologies are satisfied by every model
+        for clause in cnf
+            .clauses
+            .iter()
+            .filter_map(|clause| normalize(clause.clone()))
+        {
+            preprocessor.add(clause);
+        }
+        preprocessor
+    }
+
+    fn add(&mut self, clause: Clause) {
+        if clause.is_empty() {
+            self.unsat = true;
+        }
+        let index = self.clauses.len();
+        for &lit in &clause {
+            self.occurs[lit].push(index);
+        }
+        self.clauses.push(clause);
+        self.removed.push(false);
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.removed[index] = true;
+        for &lit in &self.clauses[index] {
+            self.occurs[lit].retain(|&i| i != index);
+        }
+    }
+
+    /// Replace the clause by another one, unless it is a tautology
+    fn replace(&mut self, index: usize, clause: Clause) {
+        self.remove(index);
+        if let Some(clause) = normalize(clause) {
+            self.add(clause);
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    pub fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    /// The number of eliminated vars
+    pub fn eliminated(&self) -> usize {
+        self.eliminated
+            .iter()
+            .filter(|&&eliminated| eliminated)
+            .count()
+    }
+
+    /// The simplified formula, on the same vars
+    pub fn cnf(&self) -> Cnf {
+        let mut clauses: Vec<Clause> = (0..self.clauses.len())
+            .filter(|&index| !self.removed[index])
+            .map(|index| self.clauses[index].clone())
+            .collect();
+        if self.unsat {
+            clauses.push(vec![]);
+        }
+        Cnf {
+            var_num: self.var_num,
+            cl_num: clauses.len(),
+            clauses,
+        }
+    }
+
+    /// Propagate the literals of the trail from the start, returns false on a conflict.
+    /// The number of visited literals is added to the ticks.
+    fn propagate(
+        &self,
+        values: &mut [BoolValue],
+        trail: &mut Vec<Lit>,
+        start: usize,
+        ticks: &mut usize,
+    ) -> bool {
+        let mut head = start;
+        while head < trail.len() {
+            let lit = trail[head];
+            head += 1;
+            for &index in &self.occurs[!lit] {
+                let clause = &self.clauses[index];
+                *ticks += clause.len();
+                if clause.iter().any(|&l| l.value(values) == BoolValue::True) {
+                    continue;
+                }
+                let mut unassigned = clause
+                    .iter()
+                    .filter(|&&l| l.value(values) == BoolValue::Undefined);
+                match (unassigned.next(), unassigned.next()) {
+                    (None, _) => return false,
+                    (Some(&unit), None) => {
+                        values[unit.get_var().0 as usize] = BoolValue::from(unit.is_neg() as i8);
+                        trail.push(unit);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        true
+    }
+
+    /// Assign the literal and propagate it, returns false on a conflict
+    fn assign(
+        &self,
+        lit: Lit,
+        values: &mut [BoolValue],
+        trail: &mut Vec<Lit>,
+        ticks: &mut usize,
+    ) -> bool {
+        match lit.value(values) {
+            BoolValue::True => true,
+            BoolValue::False => false,
+            BoolValue::Undefined => {
+                let start = trail.len();
+                values[lit.get_var().0 as usize] = BoolValue::from(lit.is_neg() as i8);
+                trail.push(lit);
+                self.propagate(values, trail, start, ticks)
+            }
+        }
+    }
+
+    /// Apply all the techniques, each one on the formula simplified by the previous ones
+    pub fn simplify(&mut self) {
+        self.probe();
+        self.substitute_equivalences();
+        self.subsume();
+        self.eliminate_vars();
+        self.eliminate_blocked();
+    }
+
+    /// Find the literals implied at level 0 by failed literal probing: if assigning a literal
+    /// and propagating it leads to a conflict, its negation is implied, and the literals implied by both
+    /// values of a var are implied. The vars of the binary clauses are probed.
+    /// The clauses are then simplified by the implied literals, which are kept as unit clauses.
+    pub fn probe(&mut self) {
+        let mut values = vec![BoolValue::Undefined; self.var_num];
+        // The literals implied at level 0
+        let mut units = vec![];
+        let mut ticks = 0;
+        for index in 0..self.clauses.len() {
+            if !self.removed[index] && self.clauses[index].len() == 1 {
+                let lit = self.clauses[index][0];
+                if !self.assign(lit, &mut values, &mut units, &mut ticks) {
+                    self.unsat = true;
+                    return;
+                }
+            }
+        }
+        let mut probed = vec![false; self.var_num];
+        for index in 0..self.clauses.len() {
+            if self.budget.exhausted() {
+                break;
+            }
+            if self.removed[index] || self.clauses[index].len() != 2 {
+                continue;
+            }
+            let vars: Vec<Var> = self.clauses[index]
+                .iter()
+                .map(|lit| lit.get_var())
+                .collect();
+            for var in vars {
+                if probed[var] || values[var] != BoolValue::Undefined || ticks > PROBING_LIMIT {
+                    continue;
+                }
+                probed[var] = true;
+                let level = units.len();
+                let (pos, neg) = (Lit::new(var, false), Lit::new(var, true));
+                let mut implied = vec![];
+                // Try both values, the necessary assignments are implied by both
+                let mut failed = None;
+                for lit in [pos, neg] {
+                    let ok = self.assign(lit, &mut values, &mut units, &mut ticks);
+                    if !ok {
+                        failed = Some(lit);
+                    } else if lit == pos {
+                        implied = units[level + 1..].to_vec();
+                    } else {
+                        implied.retain(|&l| l.value(&values) == BoolValue::True);
+                    }
+                    for lit in units.drain(level..) {
+                        values[lit.get_var()] = BoolValue::Undefined;
+                    }
+                    if failed.is_some() {
+                        break;
+                    }
+                }
+                let implied = match failed {
+                    Some(lit) => vec![!lit],
+                    None => implied,
+                };
+                for lit in implied {
+                    if !self.assign(lit, &mut values, &mut units, &mut ticks) {
+                        self.unsat = true;
+                        return;
+                    }
+                }
+            }
+        }
+
+        let touched: Vec<usize> = units
+            .iter()
+            .flat_map(|&lit| self.occurs[lit].iter().chain(&self.occurs[!lit]))
+            .copied()
+            .collect();
+        // The clauses left as they are stay implied by the units
+        for index in touched {
+            if self.budget.exhausted() {
+                break;
+            }
+            if self.removed[index] {
+                continue;
+            }
+            let clause = &self.clauses[index];
+            if clause
+                .iter()
+                .any(|&lit| lit.value(&values) == BoolValue::True)
+            {
+                self.remove(index);
+            } else {
+                let clause = clause
+                    .iter()
+                    .copied()
+                    .filter(|&lit| lit.value(&values) == BoolValue::Undefined)
+                    .collect();
+                self.replace(index, clause);
+            }
+        }
+        for lit in units {
+            self.add(vec![lit]);
+        }
+    }
+
+    /// Replace each literal by the representative of its class of equivalent literals, the literal
+    /// with the lowest var of its strongly connected component in the implication graph of
+    /// the binary clauses. The replaced vars are eliminated: their value is the one of their representative.
+    pub fn substitute_equivalences(&mut self) {
+        if self.budget.exhausted() {
+            return;
+        }
+        // The node of each literal is its code
+        let lits: Vec<Lit> = (0..self.var_num)
+            .flat_map(|var| [false, true].map(|neg| Lit::new(Var::from_id(var), neg)))
+            .collect();
+        let mut graph: DiGraph<Lit, ()> = DiGraph::new();
+        for &lit in &lits {
+            graph.add_node(lit);
+        }
+        let node = |lit: Lit| NodeIndex::new(lit.code() as usize);
+        for index in 0..self.clauses.len() {
+            if !self.removed[index] && self.clauses[index].len() == 2 {
+                let (a, b) = (self.clauses[index][0], self.clauses[index][1]);
+                graph.add_edge(node(!a), node(b), ());
+                graph.add_edge(node(!b), node(a), ());
+            }
+        }
+        let mut representative = lits;
+        for scc in petgraph::algo::tarjan_scc(&graph) {
+            let lits: Vec<Lit> = scc.iter().map(|&node| graph[node]).collect();
+            let Some(&min) = lits.iter().min_by_key(|lit| lit.get_var()) else {
+                continue;
+            };
+            for &lit in &lits {
+                // The component of the negations is the mirror of this one, so it contains
+                // the negation of each of its literals, in particular of the representative
+                if lit == !min {
+                    // A literal equivalent to its negation
+                    self.unsat = true;
+                    return;
+                }
+                representative[lit] = min;
+                representative[!lit] = !min;
+            }
+        }
+
+        let mut touched = vec![];
+        for var in (0..self.var_num).map(Var::from_id) {
+            let lit = Lit::new(var, false);
+            let repr = representative[lit];
+            if repr == lit {
+                continue;
+            }
+            // The clauses lit ∨ ¬repr and ¬lit ∨ repr set the var to the value of its representative
+            self.eliminated[var] = true;
+            self.stack.push(lit, vec![lit, !repr]);
+            self.stack.push(!lit, vec![!lit, repr]);
+            touched.extend(self.occurs[lit].iter().chain(&self.occurs[!lit]).copied());
+        }
+        touched.sort();
+        touched.dedup();
+        for index in touched {
+            let clause = self.clauses[index]
+                .iter()
+                .map(|&lit| representative[lit])
+                .collect();
+            self.replace(index, clause);
+        }
+    }
+
+    /// Remove the subsumed clauses and strengthen the other ones.
+    /// The removed clauses are implied by the remaining ones, so the models are not changed.
+    pub fn subsume(&mut self) {
+        if self.budget.exhausted() {
+            return;
+        }
+        let mut subsumer = Subsumer::new(self.var_num);
+        let mut count = 0;
+        for index in 0..self.clauses.len() {
+            if !self.removed[index] {
+                subsumer.add(self.clauses[index].clone(), true);
+                count += 1;
+            }
+        }
+        if subsumer.run().is_empty() {
+            return;
+        }
+        // The clauses are numbered again from 0
+        self.clauses.clear();
+        self.removed.clear();
+        self.occurs.iter_mut().for_each(|occurs| occurs.clear());
+        for index in 0..count {
+            if !subsumer.is_removed(index) {
+                self.add(subsumer.clause(index).to_vec());
+            }
+        }
+    }
+
+    /// Eliminate vars until none can be, the cheapest first.
+    /// The vars of the added resolvents are tried again.
+    pub fn eliminate_vars(&mut self) {
+        let cost = |occurs: &Vec<Vec<usize>>, var: Var| {
+            occurs[Lit::new(var, false)].len() * occurs[Lit::new(var, true)].len()
+        };
+        let mut queue: Vec<Var> = (0..self.var_num).map(Var::from_id).collect();
+        // The cheapest are popped first
+        queue.sort_by_key(|&var| std::cmp::Reverse(cost(&self.occurs, var)));
+        let mut queued = vec![true; self.var_num];
+        while let Some(var) = queue.pop() {
+            queued[var] = false;
+            if self.unsat || self.budget.exhausted() {
+                break;
+            }
+            let start = self.clauses.len();
+            if self.eliminated[var] || !self.eliminate(var) {
+                continue;
+            }
+            for index in start..self.clauses.len() {
+                for lit in &self.clauses[index] {
+                    if !queued[lit.get_var()] {
+                        queued[lit.get_var()] = true;
+                        queue.push(lit.get_var());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Try to eliminate the var, returns whether it was
+    fn eliminate(&mut self, var: Var) -> bool {
+        let (pos, neg) = (Lit::new(var, false), Lit::new(var, true));
+        let (pos_occurs, neg_occurs) = (self.occurs[pos].clone(), self.occurs[neg].clone());
+        if pos_occurs.len() + neg_occurs.len() == 0
+            || (pos_occurs.len() > OCCURRENCE_LIMIT && neg_occurs.len() > OCCURRENCE_LIMIT)
+        {
+            return false;
+        }
+        let limit = pos_occurs.len() + neg_occurs.len() + CLAUSE_GROWTH;
+        let mut resolvents = vec![];
+        for &p in &pos_occurs {
+            for &n in &neg_occurs {
+                if let Some(resolvent) = self.resolve(p, n, var) {
+                    if resolvents.len() == limit || resolvent.len() > RESOLVENT_LIMIT {
+                        return false;
+                    }
+                    resolvents.push(resolvent);
+                }
+            }
+        }
+
+        self.eliminated[var] = true;
+        for (lit, occurs) in [(pos, pos_occurs), (neg, neg_occurs)] {
+            for index in occurs {
+                self.stack.push(lit, self.clauses[index].clone());
+                self.remove(index);
+            }
+        }
+        for resolvent in resolvents {
+            self.add(resolvent);
+        }
+        true
+    }
+
+    /// The resolvent of the clauses on the var, None if it is a tautology
+    fn resolve(&self, p: usize, n: usize, var: Var) -> Option<Clause> {
+        let resolvent: Clause = self.clauses[p]
+            .iter()
+            .chain(self.clauses[n].iter())
+            .copied()
+            .filter(|lit| lit.get_var() != var)
+            .collect();
+        normalize(resolvent)
+    }
+
+    /// Remove the clauses blocked on one of their literals: their resolvents on it with all the clauses
+    /// containing its negation are tautologies, so a model can always satisfy them by making it true.
+    /// The clauses containing the negation of a literal of a removed clause are checked again.
+    pub fn eliminate_blocked(&mut self) {
+        let mut queue: Vec<Lit> = (0..self.var_num)
+            .flat_map(|var| [false, true].map(|neg| Lit::new(Var::from_id(var), neg)))
+            .collect();
+        let mut queued = vec![true; 2 * self.var_num];
+        while let Some(lit) = queue.pop() {
+            if self.budget.exhausted() {
+                break;
+            }
+            queued[lit] = false;
+            if self.occurs[!lit].len() > OCCURRENCE_LIMIT {
+                continue;
+            }
+            for index in self.occurs[lit].clone() {
+                if !self.is_blocked(index, lit) {
+                    continue;
+                }
+                let clause = self.clauses[index].clone();
+                self.remove(index);
+                for &other in &clause {
+                    if !queued[!other] {
+                        queued[!other] = true;
+                        queue.push(!other);
+                    }
+                }
+                self.stack.push(lit, clause);
+            }
+        }
+    }
+
+    fn is_blocked(&self, index: usize, lit: Lit) -> bool {
+        let clause = &self.clauses[index];
+        self.occurs[!lit].iter().all(|&other| {
+            self.clauses[other]
+                .iter()
+                .any(|&l| l != !lit && clause.contains(&!l))
+        })
+    }
+
+    /// Extend a model of the simplified formula to a model of the original one
+    pub fn extend_model(&self, model: &mut [BoolValue]) {
+        self.stack.extend(model);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::all_types::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// The formats of the proofs of unsatisfiability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofFormat {
+    /// A line is a clause in dimacs format, prefixed by "d" for deletions
+    Drat,
+    /// Each clause starts with the byte 'a' or 'd' and its literals are written as variable length
+    /// integers, ended by a 0
+    BinaryDrat,
+    /// A line is the id of the clause, its literals ended by a 0 and the ids of the clauses (hints)
+    /// that become unit in order when its literals are false, ended by a 0.
+    /// Deletions are written "<last id> d <ids> 0"
+    Lrat,
+}
+
+/// Writer of a proof: the clauses learnt and deleted by the solver, in order.
+/// If the formula is unsatisfiable, the proof ends with the empty clause and can be checked by
+/// standard proof checkers such as drat-trim and cake_lpr.
+/// The ids and hints of the clauses are only used by LRAT.
+/// The first write error stops the writing, it is kept to be reported by `error`.
+#[derive(Debug)]
+pub struct Proof {
+    out: BufWriter<File>,
+    format: ProofFormat,
+    // The id of the last added clause, which starts the deletion lines of LRAT
+    last_id: u64,
+    error: Option<std::io::Error>,
+}
+
+impl Proof {
+    pub fn create(path: &str, format: ProofFormat) -> std::io::Result<Self> {
+        Ok(Proof {
+            out: BufWriter::new(File::create(path)?),
+            format,
+            last_id: 0,
+            error: None,
+        })
+    }
+
+    /// Whether the clauses have to be added with their hints
+    pub fn is_lrat(&self) -> bool {
+        self.format == ProofFormat::Lrat
+    }
+
+    /// The first error met while writing the proof, if any
+    pub fn error(&self) -> Option<&std::io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn add(&mut self, id: u64, clause: &[Lit], hints: &[u64]) {
+        self.last_id = id;
+        if self.error.is_some() {
+            return;
+        }
+        let result = match self.format {
+            ProofFormat::Drat => self.write_text(false, clause),
+            ProofFormat::BinaryDrat => self.write_binary(false, clause),
+            ProofFormat::Lrat => self.write_lrat(id, clause, hints),
+        };
+        self.error = result.err();
+    }
+
+    pub fn delete(&mut self, id: u64, clause: &[Lit]) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = match self.format {
+            ProofFormat::Drat => self.write_text(true, clause),
+            ProofFormat::BinaryDrat => self.write_binary(true, clause),
+            ProofFormat::Lrat => writeln!(self.out, "{} d {id} 0", self.last_id),
+        };
+        self.error = result.err();
+    }
+
+    fn write_text(&mut self, deletion: bool, clause: &[Lit]) -> std::io::Result<()> {
+        if deletion {
+            write!(self.out, "d ")?;
+        }
+        for &lit in clause {
+            write!(self.out, "{} ", i32::from(lit))?;
+        }
+        writeln!(self.out, "0")
+    }
+
+    fn write_binary(&mut self, deletion: bool, clause: &[Lit]) -> std::io::Result<()> {
+        self.out.write_all(if deletion { b"d" } else { b"a" })?;
+        for &lit in clause {
+            // 2 * (var + 1) + sign, 7 bits at a time, the high bit telling whether more follow
+            let mut x = lit.code() + 2;
+            while x > 127 {
+                self.out.write_all(&[(x & 127) as u8 | 128])?;
+                x >>= 7;
+            }
+            self.out.write_all(&[x as u8])?;
+        }
+        self.out.write_all(&[0])
+    }
+
+    fn write_lrat(&mut self, id: u64, clause: &[Lit], hints: &[u64]) -> std::io::Result<()> {
+        write!(self.out, "{id} ")?;
+        for &lit in clause {
+            write!(self.out, "{} ", i32::from(lit))?;
+        }
+        write!(self.out, "0 ")?;
+        for hint in hints {
+            write!(self.out, "{hint} ")?;
+        }
+        writeln!(self.out, "0")
+    }
+
+    pub fn flush(&mut self) {
+        if self.error.is_none() {
+            self.error = self.out.flush().err();
+        }
+    }
+}
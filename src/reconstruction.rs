@@ -0,0 +1,37 @@
+use crate::all_types::*;
+
+/// The clauses removed by the simplifications which do not keep the models of the formula,
+/// each one with the literal to make true when it is not satisfied.
+/// A var eliminated by resolution pushes all its clauses, a blocked clause is pushed with the literal
+/// it is blocked on, and a literal replaced by an equivalent one pushes the two binary clauses
+/// defining it.
+/// Going through the stack backward turns a model of the simplified formula into a model of
+/// the original one, whatever the order in which the techniques were applied.
+#[derive(Debug, Default)]
+pub struct ReconstructionStack {
+    clauses: Vec<(Lit, Clause)>,
+}
+
+impl ReconstructionStack {
+    pub fn push(&mut self, lit: Lit, clause: Clause) {
+        self.clauses.push((lit, clause));
+    }
+
+    /// Extend a model of the simplified formula to a model of the original one.
+    /// The unassigned vars are set to false first.
+    pub fn extend(&self, model: &mut [BoolValue]) {
+        for value in model.iter_mut() {
+            if *value == BoolValue::Undefined {
+                *value = BoolValue::False;
+            }
+        }
+        let is_true = |model: &[BoolValue], lit: Lit| {
+            model[lit.get_var().0 as usize] == BoolValue::from(lit.is_neg() as i8)
+        };
+        for (lit, clause) in self.clauses.iter().rev() {
+            if !clause.iter().any(|&l| is_true(model, l)) {
+                model[lit.get_var().0 as usize] = BoolValue::from(lit.is_neg() as i8);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::Restart;
+use std::collections::VecDeque;
+
+/// The i-th element (from 0) of the Luby sequence: 1 1 2 1 1 2 4 1 1 2 1 1 2 4 8 ...
+pub fn luby(mut i: u64) -> u64 {
+    // Find the finite subsequence that contains index i, and the size of that subsequence
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+/// Decide when the CDCL solver should restart.
+#[derive(Debug)]
+pub struct Restarts {
+    policy: Restart,
+    restarts: u64,
+    // The conflicts since the last restart
+    conflicts: u64,
+    // The number of conflicts before the next restart, for static policies
+    limit: u64,
+    // The LBDs of the last learnt clauses and their sum
+    recent_lbds: VecDeque<u32>,
+    recent_sum: u64,
+    // The sum and number of LBDs of all the learnt clauses
+    total_sum: u64,
+    total: u64,
+}
+
+impl Restarts {
+    pub fn new(policy: Restart) -> Self {
+        let mut restarts = Restarts {
+            policy,
+            restarts: 0,
+            conflicts: 0,
+            limit: 0,
+            recent_lbds: VecDeque::new(),
+            recent_sum: 0,
+            total_sum: 0,
+            total: 0,
+        };
+        restarts.update_limit();
+        restarts
+    }
+
+    fn update_limit(&mut self) {
+        self.limit = match self.policy {
+            Restart::Never | Restart::Glucose { .. } => u64::MAX,
+            Restart::Luby { unit } => luby(self.restarts) * unit,
+            Restart::Geometric { first, factor } => {
+                (first as f64 * factor.powi(self.restarts as i32)) as u64
+            }
+        }
+    }
+
+    /// Called on each conflict with the LBD of the learnt clause
+    pub fn on_conflict(&mut self, lbd: u32) {
+        self.conflicts += 1;
+        if let Restart::Glucose { window, .. } = self.policy {
+            self.total += 1;
+            self.total_sum += lbd as u64;
+            self.recent_lbds.push_back(lbd);
+            self.recent_sum += lbd as u64;
+            if self.recent_lbds.len() > window {
+                self.recent_sum -= self.recent_lbds.pop_front().unwrap() as u64;
+            }
+        }
+    }
+
+    pub fn should_restart(&self) -> bool {
+        match self.policy {
+            Restart::Glucose { window, margin } => {
+                self.recent_lbds.len() == window
+                    && self.recent_sum as f64 / window as f64 * margin
+                        > self.total_sum as f64 / self.total as f64
+            }
+            _ => self.conflicts >= self.limit,
+        }
+    }
+
+    /// Called once the solver restarted
+    pub fn restarted(&mut self) {
+        self.restarts += 1;
+        self.conflicts = 0;
+        self.recent_lbds.clear();
+        self.recent_sum = 0;
+        self.update_limit();
+    }
+}
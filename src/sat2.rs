@@ -1,4 +1,5 @@
 use crate::all_types::*;
+use crate::solver::{Budget, Limits, SolveResult, Terminator};
 use petgraph::graph::DiGraph;
 
 /// A solver for 2SAT formulae.
@@ -8,6 +9,7 @@ pub struct SAT2 {
     impl_graph: DiGraph<Lit, ()>,
     pub status: Option<bool>,
     pub assigns: Vec<BoolValue>,
+    budget: Budget,
 }
 
 impl<'a> crate::solver::Solver<'a> for SAT2 {
@@ -17,12 +19,14 @@ impl<'a> crate::solver::Solver<'a> for SAT2 {
                 impl_graph: DiGraph::new(),
                 status: Some(true),
                 assigns: vec![],
+                budget: Budget::default(),
             };
         } else if cnf.clauses[0].is_empty() {
             return SAT2 {
                 impl_graph: DiGraph::new(),
                 status: Some(false),
                 assigns: vec![],
+                budget: Budget::default(),
             };
         }
         let mut impl_graph = DiGraph::new();
@@ -52,13 +56,31 @@ impl<'a> crate::solver::Solver<'a> for SAT2 {
             impl_graph,
             status: None,
             assigns: vec![BoolValue::Undefined; cnf.var_num],
+            budget: Budget::default(),
         }
     }
 
-    fn solve(&mut self) -> bool {
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    fn solve(&mut self) -> SolveResult {
+        self.budget.reset();
         if let Some(status) = self.status {
-            return status;
+            return SolveResult::from(status);
         };
+        // Only the time limit can be reached before the components are found
+        if self.budget.exhausted() {
+            return SolveResult::Unknown;
+        }
         let sccs = petgraph::algo::tarjan_scc(&self.impl_graph);
         for scc in sccs {
             let mut all_literals = std::collections::HashSet::new();
@@ -66,16 +88,20 @@ impl<'a> crate::solver::Solver<'a> for SAT2 {
                 let lit = self.impl_graph[node_lit];
                 if all_literals.contains(&!lit) {
                     self.status = Some(false);
-                    return false;
+                    return SolveResult::Unsat;
                 }
                 all_literals.insert(lit);
                 if self.assigns[lit.get_var()] == BoolValue::Undefined {
+                    self.budget.propagations += 1;
+                    if self.budget.exhausted() {
+                        return SolveResult::Unknown;
+                    }
                     self.assigns[lit.get_var()] = BoolValue::from(lit.is_neg() as i8);
                 }
             }
         }
         self.status = Some(true);
-        true
+        SolveResult::Sat
     }
 
     fn assigns(&mut self) -> &Vec<BoolValue> {
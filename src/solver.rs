@@ -1,5 +1,132 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The answer of a solver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveResult {
+    Sat,
+    Unsat,
+    /// The limits were reached before the answer was found
+    Unknown,
+}
+
+impl From<bool> for SolveResult {
+    fn from(sat: bool) -> Self {
+        if sat {
+            SolveResult::Sat
+        } else {
+            SolveResult::Unsat
+        }
+    }
+}
+
+/// The resources a call to `solve` may use, there is no limit on the ones set to None.
+/// A solver only counts what it does: the Horn solver never reaches a limit on conflicts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub conflicts: Option<u64>,
+    pub propagations: Option<u64>,
+    pub decisions: Option<u64>,
+}
+
+/// A handle to stop a solver from another thread, or from a signal handler.
+/// The clones share the same flag, and a terminated solver answers Unknown until it is given another one.
+#[derive(Debug, Clone, Default)]
+pub struct Terminator(Arc<AtomicBool>);
+
+impl Terminator {
+    pub fn terminate(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The resources used by the current call to `solve`, counted by the solver, and its limits
+#[derive(Debug)]
+pub struct Budget {
+    limits: Limits,
+    terminator: Terminator,
+    start: Instant,
+    // The start of the next call, if it is not the time of the call
+    next_start: Option<Instant>,
+    pub conflicts: u64,
+    pub propagations: u64,
+    pub decisions: u64,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::new(Limits::default())
+    }
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Budget {
+            limits,
+            terminator: Terminator::default(),
+            start: Instant::now(),
+            next_start: None,
+            conflicts: 0,
+            propagations: 0,
+            decisions: 0,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn set_terminator(&mut self, terminator: Terminator) {
+        self.terminator = terminator;
+    }
+
+    /// Count the time of the next call from this instant, to include the work done before it
+    /// such as the preprocessing
+    pub fn start_at(&mut self, start: Instant) {
+        self.next_start = Some(start);
+    }
+
+    /// Start counting for a new call
+    pub fn reset(&mut self) {
+        self.start = self.next_start.take().unwrap_or_else(Instant::now);
+        self.conflicts = 0;
+        self.propagations = 0;
+        self.decisions = 0;
+    }
+
+    /// The time spent in the current call
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Whether one of the limits is reached or the solver was terminated
+    pub fn exhausted(&self) -> bool {
+        let reached = |limit: Option<u64>, used: u64| limit.is_some_and(|limit| used >= limit);
+        self.terminator.is_terminated()
+            || reached(self.limits.conflicts, self.conflicts)
+            || reached(self.limits.propagations, self.propagations)
+            || reached(self.limits.decisions, self.decisions)
+            || self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() >= time)
+    }
+}
+
 pub trait Solver<'a> {
     fn new<'b: 'a>(cnf: &'b crate::all_types::Cnf) -> Self;
-    fn solve(&mut self) -> bool;
+    /// Limit the resources used by the next calls to `solve`
+    fn set_limits(&mut self, limits: Limits);
+    /// Give a handle stopping the calls to `solve`, which then answer Unknown
+    fn set_terminator(&mut self, terminator: Terminator);
+    /// The resources used by the last call to `solve`
+    fn budget(&self) -> &Budget;
+    fn solve(&mut self) -> SolveResult;
     fn assigns(&mut self) -> &Vec<crate::all_types::BoolValue>;
-}
\ No newline at end of file
+}
@@ -0,0 +1,129 @@
+use crate::all_types::*;
+
+/// Clauses longer than this are not used to subsume others, the check would be too costly.
+const SUBSUMER_LIMIT: usize = 1000;
+
+/// A step of the simplification, in the order they happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Simplification {
+    /// The clause contains the clause `by`, so it is removed
+    Subsumed { clause: usize, by: usize },
+    /// The literal was removed from the clause: its resolvent with `by` on it is a subset of it
+    Strengthened { clause: usize, by: usize, lit: Lit },
+}
+
+/// A set of 64 bits per clause, one for each var modulo 64, to quickly rule out most of the pairs
+/// where one clause cannot subsume the other.
+fn signature(clause: &[Lit]) -> u64 {
+    clause
+        .iter()
+        .fold(0, |signature, lit| signature | 1 << (lit.get_var().0 % 64))
+}
+
+/// If c subsumes d, returns Some(None).
+/// If d is strengthened by self-subsuming resolution with c, returns Some(lit) where lit is the literal
+/// of d to remove: c contains its negation, and all the other literals of c are in d.
+fn subsumes(c: &[Lit], d: &[Lit]) -> Option<Option<Lit>> {
+    let mut removed = None;
+    for &lit in c {
+        if d.contains(&lit) {
+            continue;
+        }
+        if removed.is_none() && d.contains(&!lit) {
+            removed = Some(!lit);
+        } else {
+            return None;
+        }
+    }
+    Some(removed)
+}
+
+/// Remove the subsumed clauses and strengthen clauses by self-subsuming resolution.
+/// Only the clauses added as candidates are removed or strengthened, the other ones are only used
+/// to simplify them, so that the learnt clauses can be simplified by the original ones.
+/// Each clause tries to subsume the candidates containing its least frequent var (backward subsumption),
+/// and the strengthened clauses try again, so that the candidates are also simplified by them.
+#[derive(Debug)]
+pub struct Subsumer {
+    clauses: Vec<Clause>,
+    signatures: Vec<u64>,
+    removed: Vec<bool>,
+    // For each var, the candidates containing it
+    occurs: Vec<Vec<usize>>,
+}
+
+impl Subsumer {
+    pub fn new(var_num: usize) -> Self {
+        Subsumer {
+            clauses: vec![],
+            signatures: vec![],
+            removed: vec![],
+            occurs: vec![vec![]; var_num],
+        }
+    }
+
+    /// Add a clause without duplicate literals, the clauses are numbered from 0 in order
+    pub fn add(&mut self, clause: Clause, candidate: bool) {
+        let index = self.clauses.len();
+        if candidate {
+            for lit in &clause {
+                self.occurs[lit.get_var()].push(index);
+            }
+        }
+        self.signatures.push(signature(&clause));
+        self.clauses.push(clause);
+        self.removed.push(false);
+    }
+
+    /// The clause, once strengthened
+    pub fn clause(&self, index: usize) -> &[Lit] {
+        &self.clauses[index]
+    }
+
+    pub fn is_removed(&self, index: usize) -> bool {
+        self.removed[index]
+    }
+
+    /// Simplify the candidates until no clause subsumes or strengthens another one
+    pub fn run(&mut self) -> Vec<Simplification> {
+        let mut simplifications = vec![];
+        let mut queue: Vec<usize> = (0..self.clauses.len()).collect();
+        // The shortest clauses subsume the most, they are popped first
+        queue.sort_by_key(|&index| std::cmp::Reverse(self.clauses[index].len()));
+        while let Some(by) = queue.pop() {
+            let len = self.clauses[by].len();
+            if self.removed[by] || len == 0 || len > SUBSUMER_LIMIT {
+                continue;
+            }
+            let var = self.clauses[by]
+                .iter()
+                .map(|lit| lit.get_var())
+                .min_by_key(|&var| self.occurs[var].len())
+                .unwrap();
+            for clause in self.occurs[var].clone() {
+                if clause == by
+                    || self.removed[clause]
+                    || self.clauses[clause].len() < len
+                    || self.signatures[by] & !self.signatures[clause] != 0
+                {
+                    continue;
+                }
+                match subsumes(&self.clauses[by], &self.clauses[clause]) {
+                    None => (),
+                    Some(None) => {
+                        self.removed[clause] = true;
+                        simplifications.push(Simplification::Subsumed { clause, by });
+                    }
+                    Some(Some(lit)) => {
+                        self.clauses[clause].retain(|&l| l != lit);
+                        self.signatures[clause] = signature(&self.clauses[clause]);
+                        self.occurs[lit.get_var()].retain(|&i| i != clause);
+                        simplifications.push(Simplification::Strengthened { clause, by, lit });
+                        queue.push(clause);
+                    }
+                }
+            }
+        }
+        simplifications
+    }
+}
@@ -1,3 +1,4 @@
+use crate::solver::{Budget, Limits, SolveResult, Terminator};
 use crate::*;
 
 /// The simpliest solver one can think of.
@@ -6,6 +7,7 @@ pub struct TautoSolver<'a> {
     n: usize,
     clauses: &'a Vec<Clause>,
     pub assigns: Vec<BoolValue>,
+    budget: Budget,
 }
 
 impl<'a> solver::Solver<'a> for TautoSolver<'a> {
@@ -17,6 +19,7 @@ impl<'a> solver::Solver<'a> for TautoSolver<'a> {
             n: cnf.var_num,
             clauses: &cnf.clauses,
             assigns: vec![BoolValue::Undefined; cnf.var_num],
+            budget: Budget::default(),
         }
     }
 
@@ -24,12 +27,26 @@ impl<'a> solver::Solver<'a> for TautoSolver<'a> {
         &self.assigns
     }
 
-    fn solve(&mut self) -> bool {
+    fn set_limits(&mut self, limits: Limits) {
+        self.budget.set_limits(limits);
+    }
+
+    fn set_terminator(&mut self, terminator: Terminator) {
+        self.budget.set_terminator(terminator);
+    }
+
+    fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    fn solve(&mut self) -> SolveResult {
+        self.budget.reset();
         self.ssolve(0)
     }
 }
 
 impl<'a> TautoSolver<'a> {
+    #[allow(clippy::collapsible_match)]
     fn eval(&self) -> bool {
         for clause in self.clauses.iter() {
             let mut satisfied = false;
@@ -57,17 +74,22 @@ impl<'a> TautoSolver<'a> {
         true
     }
 
-    fn ssolve(&mut self, i: usize) -> bool {
+    fn ssolve(&mut self, i: usize) -> SolveResult {
         if i == self.n {
-            return self.eval();
+            return SolveResult::from(self.eval());
+        }
+        // Each value given to a var counts as a decision
+        self.budget.decisions += 1;
+        if self.budget.exhausted() {
+            return SolveResult::Unknown;
         }
         self.assigns[i] = BoolValue::True;
         let result = self.ssolve(i + 1);
 
-        if !result {
+        if result == SolveResult::Unsat {
             self.assigns[i] = BoolValue::False;
             return self.ssolve(i + 1);
         }
-        true
+        result
     }
 }